@@ -1,5 +1,3 @@
-use std::sync::Arc;
-
 use criterion::*;
 use glam::Vec3;
 use terminal_renderer::math::Octree;
@@ -32,8 +30,8 @@ fn octre_insert_tris(c: &mut Criterion) {
     c.bench_function("insert 5000", |b| {
         b.iter(|| {
             octree = Octree::new(Vec3::splat(-1.0), Vec3::splat(1.0));
-            for tri in &tris {
-                octree.insert(Arc::new((*tri).clone()))
+            for (i, tri) in tris.iter().enumerate() {
+                octree.insert(i, [tri.v0, tri.v1, tri.v2])
             }
         })
     });
@@ -57,3 +55,5 @@ fn octre_insert_tris(c: &mut Criterion) {
     });
 }
 
+criterion_group!(benches, octre_insert_tris);
+criterion_main!(benches);
@@ -0,0 +1,127 @@
+use std::{
+    io::{self, Write},
+    path::Path,
+};
+
+use super::color_bytes;
+use crate::{
+    math::Mesh,
+    renderer::{Camera, Framebuffer, Renderer},
+};
+
+/// Writes the colors of a framebuffer as a binary .ppm (P6)
+pub fn write_ppm<W: Write>(frame: &Framebuffer, out: &mut W) -> io::Result<()> {
+    write!(out, "P6\n{} {}\n255\n", frame.w, frame.h)?;
+    out.write_all(&rgb_bytes(frame))
+}
+
+/// Writes the colors of a framebuffer as an 8 bit RGB .png
+pub fn write_png<W: Write>(frame: &Framebuffer, out: &mut W) -> io::Result<()> {
+    let size = |n: usize| {
+        u32::try_from(n)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "image too large for .png"))
+    };
+    let mut encoder = png::Encoder::new(out, size(frame.w)?, size(frame.h)?);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&rgb_bytes(frame)))
+        .map_err(|err| match err {
+            png::EncodingError::IoError(err) => err,
+            err => io::Error::new(io::ErrorKind::InvalidInput, err),
+        })
+}
+
+fn rgb_bytes(frame: &Framebuffer) -> Vec<u8> {
+    frame.color.iter().flat_map(|c| color_bytes(*c)).collect()
+}
+
+/// Largest framebuffer `check_size` accepts, with 16 bytes per pixel it takes 1 GiB
+pub const MAX_IMAGE_PIXELS: usize = 1 << 26;
+
+#[derive(Clone, Copy)]
+enum ImageFormat {
+    Png,
+    Ppm,
+}
+
+fn image_format(path: &Path) -> io::Result<ImageFormat> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("png") => Ok(ImageFormat::Png),
+        Some("ppm") => Ok(ImageFormat::Ppm),
+        _ => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "unsupported image format, expected .png or .ppm",
+        )),
+    }
+}
+
+/// Checks that a `w` by `h` framebuffer has at most `MAX_IMAGE_PIXELS` pixels
+pub fn check_size(w: usize, h: usize) -> io::Result<()> {
+    match w.checked_mul(h) {
+        Some(pixels) if pixels <= MAX_IMAGE_PIXELS => Ok(()),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("image too large, at most {MAX_IMAGE_PIXELS} pixels are supported"),
+        )),
+    }
+}
+
+/// Checks that a `w` by `h` image can be saved to `path`, to fail before rendering it
+pub fn check_image<P: AsRef<Path>>(path: P, w: usize, h: usize) -> io::Result<()> {
+    image_format(path.as_ref())?;
+    check_size(w, h)
+}
+
+/// Saves the colors of a framebuffer with the writer matching the file extension
+pub fn save_image<P: AsRef<Path>>(frame: &Framebuffer, path: P) -> io::Result<()> {
+    let path = path.as_ref();
+    match image_format(path)? {
+        ImageFormat::Png => super::write_file(path, |out| write_png(frame, out)),
+        ImageFormat::Ppm => super::write_file(path, |out| write_ppm(frame, out)),
+    }
+}
+
+/// Renders a mesh into a `w` by `h` image with square pixels and saves it like `save_image`
+pub fn save_render<P: AsRef<Path>>(
+    mesh: &Mesh,
+    camera: &Camera,
+    w: usize,
+    h: usize,
+    path: P,
+) -> io::Result<()> {
+    check_image(&path, w, h)?;
+    let mut frame = Framebuffer::new(w, h);
+    Renderer::new(1.).render(camera, mesh, &mut frame);
+    save_image(&frame, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ppm_header() {
+        let mut frame = Framebuffer::new(2, 1);
+        frame.color[1] = glam::Vec3::new(255., 128., 0.);
+        let mut out = Vec::new();
+        write_ppm(&frame, &mut out).unwrap();
+        assert_eq!(out, b"P6\n2 1\n255\n\0\0\0\xff\x80\0");
+    }
+
+    #[test]
+    fn checks_before_rendering() {
+        assert!(check_image("frame.png", 800, 600).is_ok());
+        assert!(check_image("frame.PPM", 800, 600).is_ok());
+        let err = check_image("frame.jpg", 800, 600).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+        let err = check_image("frame.png", usize::MAX, 2).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(check_size(MAX_IMAGE_PIXELS + 1, 1).is_err());
+    }
+}
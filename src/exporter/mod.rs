@@ -0,0 +1,125 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use glam::Vec3;
+
+use crate::{loader::ImportOptions, math::Mesh};
+
+mod image;
+mod obj;
+mod ply;
+mod stl;
+
+pub use image::{
+    check_image, check_size, save_image, save_render, write_png, write_ppm, MAX_IMAGE_PIXELS,
+};
+pub use obj::{save_obj, write_mtl, write_obj};
+pub use ply::{save_ply, write_ply};
+pub use stl::{save_stl, write_stl};
+
+/// Saves a mesh with the writer matching the file extension, using the default `ImportOptions`
+pub fn save<P: AsRef<Path>>(mesh: &Mesh, path: P) -> io::Result<()> {
+    save_with_options(mesh, path, &ImportOptions::default())
+}
+
+/// Saves a mesh in the renderer's space into a file using the axes, handedness and scale of `options`,
+/// so that loading it with the same options gives back the mesh (apart from `recenter`)
+pub fn save_with_options<P: AsRef<Path>>(
+    mesh: &Mesh,
+    path: P,
+    options: &ImportOptions,
+) -> io::Result<()> {
+    let mesh = mesh.transformed(options.transform().inverse(), Vec3::ZERO);
+    let path = path.as_ref();
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("obj") => save_obj(&mesh, path),
+        Some("stl") => save_stl(&mesh, path),
+        Some("ply") => save_ply(&mesh, path),
+        _ => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "unsupported format, expected .obj, .stl or .ply",
+        )),
+    }
+}
+
+/// Creates a file and passes a buffered writer for it to `write`
+fn write_file(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write(&mut out)?;
+    out.flush()
+}
+
+/// Rounds a color in the 0..255 range to bytes
+pub(crate) fn color_bytes(color: Vec3) -> [u8; 3] {
+    color
+        .clamp(Vec3::ZERO, Vec3::splat(255.))
+        .round()
+        .to_array()
+        .map(|c| c as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        loader::{self, UpAxis},
+        math::{MeshBuilder, Surface, Vertex},
+    };
+
+    /// A white and a red triangle
+    fn two_triangles() -> Mesh {
+        let mut builder = MeshBuilder::new();
+        let white = builder.add_surface(Surface::new(Vec3::splat(255.)));
+        let red = builder.add_surface(Surface::new(Vec3::new(255., 0., 0.)));
+        for position in [Vec3::ZERO, Vec3::X, Vec3::Y, Vec3::new(1., 1., 1.)] {
+            builder.add_vertex(Vertex::new(position));
+        }
+        builder.add_tri([0, 1, 2], white);
+        builder.add_tri([1, 3, 2], red);
+        builder.build()
+    }
+
+    #[test]
+    fn round_trip() {
+        let mesh = two_triangles();
+        let options = ImportOptions::default().with_up(UpAxis::Z).with_scale(2.);
+        let dir = std::env::temp_dir().join(format!("terminal-renderer-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        for extension in ["obj", "stl", "ply"] {
+            let path = dir.join(format!("round_trip.{extension}"));
+            save_with_options(&mesh, &path, &options).unwrap();
+            let loaded = loader::load_with_options(&path, &options).unwrap().mesh();
+
+            assert_eq!(loaded.tri_count(), mesh.tri_count(), "{extension}");
+            for tri in 0..mesh.tri_count() {
+                for (a, b) in mesh.corners(tri).iter().zip(loaded.corners(tri)) {
+                    assert!(a.abs_diff_eq(b, 1e-5), "{extension}: {a} != {b}");
+                }
+                let center = Vec3::splat(1. / 3.);
+                assert_eq!(
+                    loaded.color_at(tri, center),
+                    mesh.color_at(tri, center),
+                    "{extension}"
+                );
+            }
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn unsupported_extension() {
+        let err = save(&two_triangles(), "mesh.gltf").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    }
+}
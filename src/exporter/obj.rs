@@ -0,0 +1,77 @@
+use std::{
+    io::{self, Write},
+    path::Path,
+};
+
+use glam::Vec3;
+
+use crate::math::Mesh;
+
+/// Writes a mesh as .obj, with a `usemtl surface<index>` before the triangles of every surface
+/// when `mtllib` names the .mtl file holding them
+pub fn write_obj<W: Write>(mesh: &Mesh, out: &mut W, mtllib: Option<&str>) -> io::Result<()> {
+    if let Some(mtllib) = mtllib {
+        writeln!(out, "mtllib {mtllib}")?;
+    }
+
+    for (idx, v) in mesh.positions.iter().enumerate() {
+        write!(out, "v {} {} {}", v.x, v.y, v.z)?;
+        if let Some(c) = mesh.colors.get(idx) {
+            let c = *c / 255.;
+            write!(out, " {} {} {}", c.x, c.y, c.z)?;
+        }
+        writeln!(out)?;
+    }
+    for uv in &mesh.uvs {
+        writeln!(out, "vt {} {}", uv.x, uv.y)?;
+    }
+    for n in &mesh.normals {
+        writeln!(out, "vn {} {} {}", n.x, n.y, n.z)?;
+    }
+
+    let has_uvs = !mesh.uvs.is_empty();
+    let has_normals = !mesh.normals.is_empty();
+    let mut current_surface = None;
+    for (indices, surface) in mesh.indices.iter().zip(&mesh.tri_surfaces) {
+        if mtllib.is_some() && current_surface != Some(*surface) {
+            writeln!(out, "usemtl surface{surface}")?;
+            current_surface = Some(*surface);
+        }
+        write!(out, "f")?;
+        for idx in indices.map(|idx| idx + 1) {
+            match (has_uvs, has_normals) {
+                (true, true) => write!(out, " {idx}/{idx}/{idx}")?,
+                (true, false) => write!(out, " {idx}/{idx}")?,
+                (false, true) => write!(out, " {idx}//{idx}")?,
+                (false, false) => write!(out, " {idx}")?,
+            }
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Writes the colors of the surfaces of a mesh as `surface<index>` materials.
+/// Textures aren't written.
+pub fn write_mtl<W: Write>(mesh: &Mesh, out: &mut W) -> io::Result<()> {
+    for (idx, surface) in mesh.surfaces.iter().enumerate() {
+        let c = surface.color / 255.;
+        writeln!(out, "newmtl surface{idx}")?;
+        writeln!(out, "Kd {} {} {}", c.x, c.y, c.z)?;
+    }
+    Ok(())
+}
+
+/// Saves a mesh as .obj, along with a .mtl file of the same name unless all surfaces are white
+pub fn save_obj<P: AsRef<Path>>(mesh: &Mesh, path: P) -> io::Result<()> {
+    let path = path.as_ref();
+    let white = Vec3::splat(255.);
+    let mtl_path = path.with_extension("mtl");
+    let mtllib = if mesh.surfaces.iter().any(|surface| surface.color != white) {
+        super::write_file(&mtl_path, |out| write_mtl(mesh, out))?;
+        mtl_path.file_name().and_then(|name| name.to_str())
+    } else {
+        None
+    };
+    super::write_file(path, |out| write_obj(mesh, out, mtllib))
+}
@@ -0,0 +1,76 @@
+use std::{
+    io::{self, Write},
+    path::Path,
+};
+
+use glam::Vec3;
+
+use super::color_bytes;
+use crate::math::Mesh;
+
+/// Writes a mesh as binary little endian .ply with normals and vertex colors when the mesh has them.
+/// The colors of the surfaces are written as face colors unless they are all white.
+pub fn write_ply<W: Write>(mesh: &Mesh, out: &mut W) -> io::Result<()> {
+    let has_normals = !mesh.normals.is_empty();
+    let has_colors = !mesh.colors.is_empty();
+    let white = Vec3::splat(255.);
+    let has_face_colors = mesh.surfaces.iter().any(|surface| surface.color != white);
+
+    writeln!(out, "ply")?;
+    writeln!(out, "format binary_little_endian 1.0")?;
+    writeln!(out, "comment terminal-renderer")?;
+    writeln!(out, "element vertex {}", mesh.vertex_count())?;
+    for c in ["x", "y", "z"] {
+        writeln!(out, "property float {c}")?;
+    }
+    if has_normals {
+        for c in ["nx", "ny", "nz"] {
+            writeln!(out, "property float {c}")?;
+        }
+    }
+    if has_colors {
+        for c in ["red", "green", "blue"] {
+            writeln!(out, "property uchar {c}")?;
+        }
+    }
+    writeln!(out, "element face {}", mesh.tri_count())?;
+    writeln!(out, "property list uchar uint vertex_indices")?;
+    if has_face_colors {
+        for c in ["red", "green", "blue"] {
+            writeln!(out, "property uchar {c}")?;
+        }
+    }
+    writeln!(out, "end_header")?;
+
+    for idx in 0..mesh.vertex_count() {
+        write_floats(out, mesh.positions[idx])?;
+        if has_normals {
+            write_floats(out, mesh.normals[idx])?;
+        }
+        if has_colors {
+            out.write_all(&color_bytes(mesh.colors[idx]))?;
+        }
+    }
+    for (indices, surface) in mesh.indices.iter().zip(&mesh.tri_surfaces) {
+        out.write_all(&[3])?;
+        for idx in indices {
+            out.write_all(&idx.to_le_bytes())?;
+        }
+        if has_face_colors {
+            out.write_all(&color_bytes(mesh.surfaces[*surface as usize].color))?;
+        }
+    }
+    Ok(())
+}
+
+fn write_floats<W: Write>(out: &mut W, v: Vec3) -> io::Result<()> {
+    for c in v.to_array() {
+        out.write_all(&c.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// Saves a mesh as binary .ply
+pub fn save_ply<P: AsRef<Path>>(mesh: &Mesh, path: P) -> io::Result<()> {
+    super::write_file(path.as_ref(), |out| write_ply(mesh, out))
+}
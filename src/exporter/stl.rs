@@ -0,0 +1,56 @@
+use std::{
+    io::{self, Write},
+    path::Path,
+};
+
+use glam::Vec3;
+
+use crate::math::Mesh;
+
+/// Writes a mesh as binary .stl with flat facet normals.
+/// Triangles that aren't white get a VisCAM color, the color of their surface times their average vertex color.
+pub fn write_stl<W: Write>(mesh: &Mesh, out: &mut W) -> io::Result<()> {
+    let mut header = [0u8; 80];
+    let name = b"terminal-renderer";
+    header[..name.len()].copy_from_slice(name);
+    out.write_all(&header)?;
+    let count = u32::try_from(mesh.tri_count())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too many triangles for .stl"))?;
+    out.write_all(&count.to_le_bytes())?;
+
+    for tri in 0..mesh.tri_count() {
+        let [v0, v1, v2] = mesh.corners(tri);
+        let normal = (v1 - v0).cross(v2 - v0).normalize_or_zero();
+        for v in [normal, v0, v1, v2] {
+            for c in v.to_array() {
+                out.write_all(&c.to_le_bytes())?;
+            }
+        }
+
+        let mut color = mesh.surfaces[mesh.tri_surfaces[tri] as usize].color;
+        if !mesh.colors.is_empty() {
+            let [c0, c1, c2] = mesh.indices[tri].map(|idx| mesh.colors[idx as usize]);
+            color *= (c0 + c1 + c2) / 3. / 255.;
+        }
+        out.write_all(&viscam_attribute(color).to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// Packs a color into 5 bits per channel BGR with the valid bit set, white is left uncolored
+fn viscam_attribute(color: Vec3) -> u16 {
+    let [r, g, b] = (color.clamp(Vec3::ZERO, Vec3::splat(255.)) * 31. / 255.)
+        .round()
+        .to_array()
+        .map(|c| c as u16);
+    if [r, g, b] == [31; 3] {
+        0
+    } else {
+        0x8000 | r << 10 | g << 5 | b
+    }
+}
+
+/// Saves a mesh as binary .stl
+pub fn save_stl<P: AsRef<Path>>(mesh: &Mesh, path: P) -> io::Result<()> {
+    super::write_file(path.as_ref(), |out| write_stl(mesh, out))
+}
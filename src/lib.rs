@@ -1,4 +1,7 @@
+pub mod exporter;
 pub mod loader;
 pub mod math;
+pub mod recorder;
 pub mod renderer;
+pub mod texture;
 pub use glam;
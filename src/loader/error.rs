@@ -0,0 +1,159 @@
+use std::{
+    error::Error,
+    fmt, io,
+    path::{Path, PathBuf},
+    str::Utf8Error,
+};
+
+use crate::texture::TextureError;
+
+/// Error from loading a mesh, with the location of the problem when it is known
+#[derive(Debug)]
+pub struct MeshError {
+    pub kind: MeshErrorKind,
+    /// File the error occurred in
+    pub path: Option<PathBuf>,
+    /// 1-based line number, for text formats
+    pub line: Option<usize>,
+    /// The offending token
+    pub token: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum MeshErrorKind {
+    /// The file couldn't be read
+    Io(io::Error),
+    /// A text format contained invalid UTF-8
+    Utf8(Utf8Error),
+    /// The glTF JSON couldn't be parsed
+    Json(serde_json::Error),
+    /// A referenced texture couldn't be loaded
+    Texture(TextureError),
+    /// No loader for the file extension
+    UnsupportedFormat,
+    /// A number that should be a float isn't
+    BadFloat,
+    /// An index that isn't a non-zero integer
+    BadIndex,
+    /// An index referring to an element that hasn't been defined
+    IndexOutOfRange { index: i64, count: usize },
+    /// A statement has fewer coordinates than required
+    MissingCoordinate,
+    /// A statement has more coordinates than allowed
+    TooManyCoordinates,
+    /// A face with fewer than three vertices
+    TooFewVertices,
+    /// The file ended in the middle of the data
+    UnexpectedEof,
+    /// Any other structural problem
+    Malformed(String),
+}
+
+impl MeshError {
+    pub fn new(kind: MeshErrorKind) -> Self {
+        Self {
+            kind,
+            path: None,
+            line: None,
+            token: None,
+        }
+    }
+
+    pub fn malformed(message: impl Into<String>) -> Self {
+        Self::new(MeshErrorKind::Malformed(message.into()))
+    }
+
+    /// Sets the file of the error, unless it already happened in another file
+    pub fn in_file<P: AsRef<Path>>(self, path: P) -> Self {
+        Self {
+            path: self.path.or_else(|| Some(path.as_ref().to_path_buf())),
+            ..self
+        }
+    }
+
+    /// Sets the line of the error, unless it is already known
+    pub fn at_line(self, line: usize) -> Self {
+        Self {
+            line: self.line.or(Some(line)),
+            ..self
+        }
+    }
+
+    pub fn with_token(self, token: &str) -> Self {
+        Self {
+            token: Some(token.to_string()),
+            ..self
+        }
+    }
+}
+
+impl From<MeshErrorKind> for MeshError {
+    fn from(kind: MeshErrorKind) -> Self {
+        Self::new(kind)
+    }
+}
+
+impl From<io::Error> for MeshError {
+    fn from(err: io::Error) -> Self {
+        Self::new(MeshErrorKind::Io(err))
+    }
+}
+
+impl From<Utf8Error> for MeshError {
+    fn from(err: Utf8Error) -> Self {
+        Self::new(MeshErrorKind::Utf8(err))
+    }
+}
+
+impl fmt::Display for MeshErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshErrorKind::Io(err) => write!(f, "{err}"),
+            MeshErrorKind::Utf8(err) => write!(f, "invalid UTF-8: {err}"),
+            MeshErrorKind::Json(err) => write!(f, "invalid JSON: {err}"),
+            MeshErrorKind::Texture(err) => write!(f, "couldn't load texture: {err}"),
+            MeshErrorKind::UnsupportedFormat => write!(f, "unsupported file format"),
+            MeshErrorKind::BadFloat => write!(f, "invalid number"),
+            MeshErrorKind::BadIndex => write!(f, "invalid index"),
+            MeshErrorKind::IndexOutOfRange { index, count } => {
+                write!(f, "index {index} out of range, {count} defined")
+            }
+            MeshErrorKind::MissingCoordinate => write!(f, "missing coordinate"),
+            MeshErrorKind::TooManyCoordinates => write!(f, "too many coordinates"),
+            MeshErrorKind::TooFewVertices => write!(f, "face with fewer than 3 vertices"),
+            MeshErrorKind::UnexpectedEof => write!(f, "unexpected end of file"),
+            MeshErrorKind::Malformed(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(path) = &self.path {
+            write!(f, "{}:", path.display())?;
+            if let Some(line) = self.line {
+                write!(f, "{line}:")?;
+            }
+            write!(f, " ")?;
+        } else if let Some(line) = self.line {
+            write!(f, "line {line}: ")?;
+        }
+        write!(f, "{}", self.kind)?;
+        if let Some(token) = &self.token {
+            write!(f, " `{token}`")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for MeshError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            MeshErrorKind::Io(err) => Some(err),
+            MeshErrorKind::Utf8(err) => Some(err),
+            MeshErrorKind::Json(err) => Some(err),
+            MeshErrorKind::Texture(err) => Some(err),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,667 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use glam::{Mat3, Mat4, Quat, Vec2, Vec3, Vec4};
+use serde_json::Value;
+
+use super::{MeshError, MeshErrorKind};
+use crate::math::{Mesh, MeshBuilder, Surface, Vertex};
+use crate::texture::Texture;
+
+const GLB_MAGIC: &[u8] = b"glTF";
+const CHUNK_JSON: u32 = 0x4E4F534A;
+const CHUNK_BIN: u32 = 0x004E4942;
+const MODE_TRIANGLES: u64 = 4;
+
+/// Loads a .gltf file (with external or embedded base64 buffers) or a binary .glb file.
+/// All mesh instances in the default scene are flattened into world space.
+pub fn load_gltf<P: AsRef<Path>>(path: P) -> Result<Mesh, MeshError> {
+    let path = path.as_ref();
+    let file = std::fs::read(path).map_err(|err| MeshError::from(err).in_file(path))?;
+    // External buffers and images are referenced relative to the file
+    let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+    parse_gltf(&file, dir).map_err(|err| err.in_file(path))
+}
+
+pub(super) fn parse_gltf(file: &[u8], dir: PathBuf) -> Result<Mesh, MeshError> {
+    let (json, bin) = if file.starts_with(GLB_MAGIC) {
+        parse_glb(file)?
+    } else {
+        (file, None)
+    };
+    let doc: Value = serde_json::from_slice(json).map_err(|err| {
+        let line = err.line();
+        MeshError::new(MeshErrorKind::Json(err)).at_line(line)
+    })?;
+
+    let gltf = Gltf::new(doc, bin, dir)?;
+    gltf.load_scene()
+}
+
+/// Splits a .glb file into its JSON and binary chunk
+fn parse_glb(file: &[u8]) -> Result<(&[u8], Option<&[u8]>), MeshError> {
+    let u32_at = |pos: usize| {
+        file.get(pos..pos + 4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+            .ok_or(MeshErrorKind::UnexpectedEof)
+    };
+
+    let mut json = None;
+    let mut bin = None;
+    let mut pos = 12;
+    while pos < file.len() {
+        let len = u32_at(pos)? as usize;
+        let ty = u32_at(pos + 4)?;
+        let data = file
+            .get(pos + 8..pos + 8 + len)
+            .ok_or(MeshErrorKind::UnexpectedEof)?;
+        match ty {
+            CHUNK_JSON => json = Some(data),
+            CHUNK_BIN => bin = Some(data),
+            _ => (),
+        }
+        pos += 8 + len;
+    }
+
+    let json = json.ok_or_else(|| MeshError::malformed("missing JSON chunk"))?;
+    Ok((json, bin))
+}
+
+struct Gltf {
+    doc: Value,
+    buffers: Vec<Vec<u8>>,
+    textures: Vec<Option<Arc<Texture>>>,
+    dir: PathBuf,
+}
+
+impl Gltf {
+    fn new(doc: Value, bin: Option<&[u8]>, dir: PathBuf) -> Result<Self, MeshError> {
+        let mut buffers = Vec::new();
+        for buffer in array(&doc["buffers"]) {
+            let data = match buffer["uri"].as_str() {
+                Some(uri) => load_uri(uri, &dir)?,
+                // A buffer without uri refers to the binary chunk of a .glb file
+                None => bin
+                    .ok_or_else(|| MeshError::malformed("buffer without uri outside a .glb file"))?
+                    .to_vec(),
+            };
+            buffers.push(data);
+        }
+
+        let mut gltf = Self {
+            doc,
+            buffers,
+            textures: Vec::new(),
+            dir,
+        };
+        gltf.textures = (0..array(&gltf.doc["images"]).len())
+            .map(|image| gltf.load_image(image))
+            .collect();
+        Ok(gltf)
+    }
+
+    fn load_scene(&self) -> Result<Mesh, MeshError> {
+        let scene = self.doc["scene"].as_u64().unwrap_or(0) as usize;
+        let roots = match self.doc["scenes"].get(scene) {
+            Some(scene) => array(&scene["nodes"])
+                .iter()
+                .map(as_index)
+                .collect::<Result<Vec<_>, _>>()?,
+            // Without scenes every node that isn't a child of another node is drawn in place
+            None => {
+                let nodes = array(&self.doc["nodes"]);
+                let mut children = HashSet::new();
+                for node in nodes {
+                    for child in array(&node["children"]) {
+                        children.insert(as_index(child)?);
+                    }
+                }
+                (0..nodes.len())
+                    .filter(|node| !children.contains(node))
+                    .collect()
+            }
+        };
+
+        let mut builder = MeshBuilder::new();
+        // Surface for every material index
+        let mut surfaces = HashMap::new();
+        let mut stack: Vec<_> = roots
+            .into_iter()
+            .map(|node| (node, Mat4::IDENTITY))
+            .collect();
+        // Nodes form trees, reaching a node twice means the hierarchy has a cycle
+        let mut visited = HashSet::new();
+        while let Some((node, parent)) = stack.pop() {
+            if !visited.insert(node) {
+                return Err(MeshError::malformed(format!(
+                    "node {node} is reached more than once, the node hierarchy has a cycle"
+                )));
+            }
+            let node_value = self.doc["nodes"]
+                .get(node)
+                .ok_or_else(|| missing("node", node))?;
+            let transform = parent * node_transform(node_value)?;
+
+            if let Some(mesh) = node_value.get("mesh") {
+                let mesh = as_index(mesh)?;
+                let mesh = self.doc["meshes"]
+                    .get(mesh)
+                    .ok_or_else(|| missing("mesh", mesh))?;
+                for primitive in array(&mesh["primitives"]) {
+                    self.add_primitive(&mut builder, &mut surfaces, primitive, transform)?;
+                }
+            }
+
+            for child in array(&node_value["children"]) {
+                stack.push((as_index(child)?, transform));
+            }
+        }
+
+        Ok(builder.build())
+    }
+
+    fn add_primitive(
+        &self,
+        builder: &mut MeshBuilder,
+        surfaces: &mut HashMap<Option<usize>, u32>,
+        primitive: &Value,
+        transform: Mat4,
+    ) -> Result<(), MeshError> {
+        if primitive["mode"].as_u64().unwrap_or(MODE_TRIANGLES) != MODE_TRIANGLES {
+            return Ok(());
+        }
+        let attributes = &primitive["attributes"];
+        let normal_transform = Mat3::from_mat4(transform).inverse().transpose();
+
+        let positions: Vec<Vec3> = self
+            .read_accessor(as_index(&attributes["POSITION"])?)?
+            .into_iter()
+            .map(|p| transform.transform_point3(p.truncate()))
+            .collect();
+        let normals: Option<Vec<Vec3>> = match self.optional_attribute(attributes, "NORMAL") {
+            Some(idx) => Some(
+                self.read_accessor(as_index(idx)?)?
+                    .into_iter()
+                    .map(|n| (normal_transform * n.truncate()).normalize_or_zero())
+                    .collect(),
+            ),
+            None => None,
+        };
+        let uvs: Option<Vec<Vec2>> = match self.optional_attribute(attributes, "TEXCOORD_0") {
+            // glTF has its texture origin in the top left corner
+            Some(idx) => Some(
+                self.read_accessor(as_index(idx)?)?
+                    .into_iter()
+                    .map(|uv| Vec2::new(uv.x, 1. - uv.y))
+                    .collect(),
+            ),
+            None => None,
+        };
+        let colors: Option<Vec<Vec3>> = match self.optional_attribute(attributes, "COLOR_0") {
+            Some(idx) => Some(
+                self.read_accessor(as_index(idx)?)?
+                    .into_iter()
+                    .map(|c| c.truncate() * 255.)
+                    .collect(),
+            ),
+            None => None,
+        };
+        let indices: Vec<usize> = match primitive.get("indices") {
+            Some(idx) => self.read_indices(as_index(idx)?)?,
+            None => (0..positions.len()).collect(),
+        };
+
+        let material = primitive.get("material").map(as_index).transpose()?;
+        let surface = match surfaces.get(&material) {
+            Some(surface) => *surface,
+            None => {
+                let surface = builder.add_surface(self.material(material)?);
+                surfaces.insert(material, surface);
+                surface
+            }
+        };
+
+        let first = builder.vertex_count() as u32;
+        for (idx, position) in positions.iter().enumerate() {
+            let mut vertex = Vertex::new(*position);
+            vertex.normal = normals.as_ref().and_then(|n| n.get(idx).copied());
+            vertex.uv = uvs.as_ref().and_then(|uvs| uvs.get(idx).copied());
+            vertex.color = colors.as_ref().and_then(|c| c.get(idx).copied());
+            builder.add_vertex(vertex);
+        }
+
+        for corners in indices.chunks_exact(3) {
+            let corners: [usize; 3] = corners.try_into().unwrap();
+            if let Some(idx) = corners.iter().find(|idx| **idx >= positions.len()) {
+                return Err(MeshErrorKind::IndexOutOfRange {
+                    index: *idx as i64,
+                    count: positions.len(),
+                }
+                .into());
+            }
+            builder.add_tri(corners.map(|idx| first + idx as u32), surface);
+        }
+
+        Ok(())
+    }
+
+    /// Accessor of a vertex attribute the mesh can do without, attributes without
+    /// a buffer view are all zeros and left out like missing ones
+    fn optional_attribute<'v>(&self, attributes: &'v Value, name: &str) -> Option<&'v Value> {
+        let idx = attributes.get(name)?;
+        let has_data = idx
+            .as_u64()
+            .and_then(|accessor| self.doc["accessors"].get(accessor as usize))
+            .is_none_or(|accessor| accessor.get("bufferView").is_some());
+        // Invalid references are kept to be reported when the accessor is read
+        has_data.then_some(idx)
+    }
+
+    /// Base color and base color texture of a material
+    fn material(&self, material: Option<usize>) -> Result<Surface, MeshError> {
+        let white = Vec3::new(255., 255., 255.);
+        let Some(material) = material else {
+            return Ok(Surface::new(white));
+        };
+        let material = self.doc["materials"]
+            .get(material)
+            .ok_or_else(|| missing("material", material))?;
+        let pbr = &material["pbrMetallicRoughness"];
+
+        let color = match array(&pbr["baseColorFactor"]) {
+            [r, g, b, ..] => Vec3::new(as_f32(r)?, as_f32(g)?, as_f32(b)?) * 255.,
+            _ => white,
+        };
+
+        let texture = match pbr["baseColorTexture"].get("index") {
+            Some(texture) => self.doc["textures"]
+                .get(as_index(texture)?)
+                .and_then(|texture| texture.get("source"))
+                .and_then(|source| source.as_u64())
+                .and_then(|source| self.textures.get(source as usize).cloned().flatten()),
+            None => None,
+        };
+
+        Ok(match texture {
+            Some(texture) => Surface::new(color).with_texture(texture),
+            None => Surface::new(color),
+        })
+    }
+
+    /// Decodes an image, images that can't be loaded are left untextured
+    fn load_image(&self, image: usize) -> Option<Arc<Texture>> {
+        let image = &self.doc["images"][image];
+        let data = match (image["uri"].as_str(), image.get("bufferView")) {
+            (Some(uri), _) if uri.starts_with("data:") => load_uri(uri, &self.dir).ok()?,
+            (Some(uri), _) => {
+                return Texture::load(self.dir.join(uri)).ok().map(Arc::new);
+            }
+            (None, Some(view)) => self.buffer_view(as_index(view).ok()?).ok()?.0.to_vec(),
+            (None, None) => return None,
+        };
+        if data.starts_with(b"P3") || data.starts_with(b"P6") {
+            Texture::from_ppm(&data).ok().map(Arc::new)
+        } else {
+            Texture::from_png(data.as_slice()).ok().map(Arc::new)
+        }
+    }
+
+    /// The bytes of a buffer view and its byte stride
+    fn buffer_view(&self, view: usize) -> Result<(&[u8], Option<usize>), MeshError> {
+        let view = self.doc["bufferViews"]
+            .get(view)
+            .ok_or_else(|| missing("buffer view", view))?;
+        let buffer = as_index(&view["buffer"])?;
+        let buffer = self
+            .buffers
+            .get(buffer)
+            .ok_or_else(|| missing("buffer", buffer))?;
+        let offset = view["byteOffset"].as_u64().unwrap_or(0) as usize;
+        let len = as_index(&view["byteLength"])?;
+        let end = offset.checked_add(len).ok_or_else(too_large)?;
+        let data = buffer
+            .get(offset..end)
+            .ok_or(MeshErrorKind::UnexpectedEof)?;
+        let stride = view["byteStride"].as_u64().map(|s| s as usize);
+        Ok((data, stride))
+    }
+
+    /// Reads up to 4 components per element of an accessor as floats
+    fn read_accessor(&self, accessor: usize) -> Result<Vec<Vec4>, MeshError> {
+        Ok(self
+            .read_components(accessor)?
+            .into_iter()
+            .map(|value| Vec4::from_array(value.map(|c| c as f32)))
+            .collect())
+    }
+
+    /// Reads a scalar accessor of vertex indices
+    fn read_indices(&self, accessor: usize) -> Result<Vec<usize>, MeshError> {
+        Ok(self
+            .read_components(accessor)?
+            .into_iter()
+            .map(|value| value[0] as usize)
+            .collect())
+    }
+
+    /// Reads up to 4 components per element, as f64 to keep 32 bit integers exact
+    fn read_components(&self, accessor: usize) -> Result<Vec<[f64; 4]>, MeshError> {
+        let accessor = self.doc["accessors"]
+            .get(accessor)
+            .ok_or_else(|| missing("accessor", accessor))?;
+        let count = as_index(&accessor["count"])?;
+        let components = match accessor["type"].as_str() {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") => 4,
+            _ => {
+                return Err(MeshError::malformed("unsupported accessor type")
+                    .with_token(&accessor["type"].to_string()))
+            }
+        };
+        let component_type = as_index(&accessor["componentType"])?;
+        let size = match component_type {
+            5120 | 5121 => 1,
+            5122 | 5123 => 2,
+            5125 | 5126 => 4,
+            _ => {
+                return Err(MeshError::malformed("unsupported component type")
+                    .with_token(&component_type.to_string()))
+            }
+        };
+        let normalized = accessor["normalized"].as_bool().unwrap_or(false);
+
+        // Accessors without a buffer view are all zeros (or sparse, which isn't supported),
+        // that is no use for positions and indices and `count` isn't bounded by any data
+        let Some(view) = accessor.get("bufferView") else {
+            return Err(MeshError::malformed("accessor without a buffer view"));
+        };
+        let (data, stride) = self.buffer_view(as_index(view)?)?;
+        let offset = accessor["byteOffset"].as_u64().unwrap_or(0) as usize;
+        let element_size = size * components;
+        let stride = stride.unwrap_or(element_size);
+        if stride < element_size {
+            return Err(MeshError::malformed("byte stride smaller than an element")
+                .with_token(&stride.to_string()));
+        }
+
+        // Every element has to be in the buffer view, which bounds the count before allocating
+        let last = match count.checked_sub(1) {
+            Some(last) => last
+                .checked_mul(stride)
+                .and_then(|start| start.checked_add(offset))
+                .and_then(|start| start.checked_add(element_size))
+                .ok_or_else(too_large)?,
+            None => 0,
+        };
+        if last > data.len() {
+            return Err(MeshErrorKind::UnexpectedEof.into());
+        }
+
+        let mut result = Vec::with_capacity(count);
+        for i in 0..count {
+            let start = offset + i * stride;
+            let element = &data[start..start + element_size];
+            let mut value = [0f64; 4];
+            for (c, bytes) in element.chunks_exact(size).enumerate() {
+                let (v, max) = match component_type {
+                    5120 => (bytes[0] as i8 as f64, i8::MAX as f64),
+                    5121 => (bytes[0] as f64, u8::MAX as f64),
+                    5122 => (
+                        i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+                        i16::MAX as f64,
+                    ),
+                    5123 => (
+                        u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+                        u16::MAX as f64,
+                    ),
+                    5125 => (u32::from_le_bytes(bytes.try_into().unwrap()) as f64, 1.),
+                    _ => (f32::from_le_bytes(bytes.try_into().unwrap()) as f64, 1.),
+                };
+                // Normalized integers map onto 0..1 (or -1..1 when signed)
+                value[c] = if normalized { (v / max).max(-1.) } else { v };
+            }
+            result.push(value);
+        }
+        Ok(result)
+    }
+}
+
+/// Local transform of a node, either a matrix or translation, rotation and scale
+fn node_transform(node: &Value) -> Result<Mat4, MeshError> {
+    if let Some(matrix) = node.get("matrix") {
+        let matrix = array(matrix)
+            .iter()
+            .map(as_f32)
+            .collect::<Result<Vec<_>, _>>()?;
+        let matrix: [f32; 16] = matrix
+            .try_into()
+            .map_err(|_| MeshError::malformed("node matrix without 16 values"))?;
+        return Ok(Mat4::from_cols_array(&matrix));
+    }
+
+    let floats = |key: &str| {
+        array(&node[key])
+            .iter()
+            .map(as_f32)
+            .collect::<Result<Vec<_>, _>>()
+    };
+    let translation = match floats("translation")?.as_slice() {
+        [x, y, z] => Vec3::new(*x, *y, *z),
+        _ => Vec3::ZERO,
+    };
+    let rotation = match floats("rotation")?.as_slice() {
+        [x, y, z, w] => Quat::from_xyzw(*x, *y, *z, *w).normalize(),
+        _ => Quat::IDENTITY,
+    };
+    let scale = match floats("scale")?.as_slice() {
+        [x, y, z] => Vec3::new(*x, *y, *z),
+        _ => Vec3::ONE,
+    };
+    Ok(Mat4::from_scale_rotation_translation(
+        scale,
+        rotation,
+        translation,
+    ))
+}
+
+/// Loads a `data:` uri or a file relative to `dir`
+fn load_uri(uri: &str, dir: &Path) -> Result<Vec<u8>, MeshError> {
+    match uri.strip_prefix("data:") {
+        Some(data) => {
+            let (_, data) = data
+                .split_once(";base64,")
+                .ok_or_else(|| MeshError::malformed("data uri isn't base64"))?;
+            decode_base64(data)
+        }
+        None => {
+            let path = dir.join(percent_decode(uri));
+            std::fs::read(&path).map_err(|err| MeshError::from(err).in_file(path))
+        }
+    }
+}
+
+fn decode_base64(data: &str) -> Result<Vec<u8>, MeshError> {
+    let mut result = Vec::with_capacity(data.len() * 3 / 4);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for c in data.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            _ => {
+                return Err(MeshError::malformed("invalid base64 character")
+                    .with_token(&(c as char).to_string()))
+            }
+        };
+        acc = (acc << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            result.push((acc >> bits) as u8);
+        }
+    }
+    Ok(result)
+}
+
+/// Uris may escape characters such as spaces in file names
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                result.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                result.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&result).into_owned()
+}
+
+fn array(value: &Value) -> &[Value] {
+    value.as_array().map(Vec::as_slice).unwrap_or_default()
+}
+
+fn as_index(value: &Value) -> Result<usize, MeshError> {
+    value
+        .as_u64()
+        .map(|v| v as usize)
+        .ok_or_else(|| MeshError::new(MeshErrorKind::BadIndex).with_token(&value.to_string()))
+}
+
+fn as_f32(value: &Value) -> Result<f32, MeshError> {
+    value
+        .as_f64()
+        .map(|v| v as f32)
+        .ok_or_else(|| MeshError::new(MeshErrorKind::BadFloat).with_token(&value.to_string()))
+}
+
+/// Error for a byte range that doesn't fit in memory
+fn too_large() -> MeshError {
+    MeshError::malformed("byte offset or length too large")
+}
+
+/// Error for a reference to an object that isn't in the document
+fn missing(kind: &str, index: usize) -> MeshError {
+    MeshError::malformed(format!("reference to missing {kind} {index}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A triangle in an embedded buffer, with the given nodes and scenes
+    fn triangle_gltf(nodes: &str, scenes: &str) -> String {
+        format!(
+            r#"{{
+                "buffers": [{{
+                    "byteLength": 36,
+                    "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAA"
+                }}],
+                "bufferViews": [{{ "buffer": 0, "byteLength": 36 }}],
+                "accessors": [{{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" }}],
+                "meshes": [{{ "primitives": [{{ "attributes": {{ "POSITION": 0 }} }}] }}],
+                "nodes": {nodes}
+                {scenes}
+            }}"#
+        )
+    }
+
+    fn parse(gltf: &str) -> Result<Mesh, MeshError> {
+        parse_gltf(gltf.as_bytes(), PathBuf::new())
+    }
+
+    #[test]
+    fn scene_nodes() {
+        let nodes = r#"[{ "mesh": 0, "children": [1] }, { "mesh": 0 }, { "mesh": 0 }]"#;
+        let mesh = parse(&triangle_gltf(nodes, r#", "scenes": [{ "nodes": [0] }]"#)).unwrap();
+        assert_eq!(mesh.tri_count(), 2);
+    }
+
+    #[test]
+    fn node_cycle() {
+        let nodes = r#"[{ "mesh": 0, "children": [1] }, { "mesh": 0, "children": [0] }]"#;
+        let err = parse(&triangle_gltf(nodes, r#", "scenes": [{ "nodes": [0] }]"#)).unwrap_err();
+        assert!(matches!(err.kind, MeshErrorKind::Malformed(_)), "{err}");
+
+        let nodes = r#"[{ "mesh": 0, "children": [0] }]"#;
+        assert!(parse(&triangle_gltf(nodes, r#", "scenes": [{ "nodes": [0] }]"#)).is_err());
+    }
+
+    #[test]
+    fn accessor_out_of_bounds() {
+        let gltf = triangle_gltf(r#"[{ "mesh": 0 }]"#, "");
+        for (from, to) in [
+            (r#""count": 3"#, r#""count": 4"#),
+            (r#""count": 3"#, r#""count": 18446744073709551615"#),
+            (
+                r#""componentType""#,
+                r#""byteOffset": 18446744073709551615, "componentType""#,
+            ),
+            (
+                r#""buffer": 0,"#,
+                r#""buffer": 0, "byteOffset": 18446744073709551615,"#,
+            ),
+            (r#""buffer": 0,"#, r#""buffer": 0, "byteStride": 0,"#),
+            (
+                r#""buffer": 0,"#,
+                r#""buffer": 0, "byteStride": 9223372036854775807,"#,
+            ),
+            // Without a buffer view nothing bounds the count
+            (r#""bufferView": 0, "#, ""),
+            (
+                r#""bufferView": 0, "componentType": 5126, "count": 3"#,
+                r#""componentType": 5126, "count": 1152921504606846975"#,
+            ),
+        ] {
+            let err = parse(&gltf.replacen(from, to, 1)).unwrap_err();
+            assert!(
+                matches!(
+                    err.kind,
+                    MeshErrorKind::UnexpectedEof | MeshErrorKind::Malformed(_)
+                ),
+                "{to}: {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn zero_attributes() {
+        // Normals without a buffer view are left out instead of read as zeros
+        let gltf = triangle_gltf(r#"[{ "mesh": 0 }]"#, "")
+            .replace(r#""POSITION": 0"#, r#""POSITION": 0, "NORMAL": 1"#)
+            .replace(
+                r#""type": "VEC3" }"#,
+                r#""type": "VEC3" }, { "componentType": 5126, "count": 1152921504606846975, "type": "VEC3" }"#,
+            );
+        let mesh = parse(&gltf).unwrap();
+        assert_eq!(mesh.normals[0].length(), 1.);
+    }
+
+    #[test]
+    fn roots_without_scenes() {
+        // The child is only drawn as part of its parent
+        let nodes = r#"[{ "mesh": 0, "children": [1] }, { "mesh": 0, "translation": [1, 0, 0] }]"#;
+        let mesh = parse(&triangle_gltf(nodes, "")).unwrap();
+        assert_eq!(mesh.tri_count(), 2);
+        assert_eq!(mesh.bounds().unwrap().1.x, 2.);
+    }
+}
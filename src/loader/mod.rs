@@ -1,104 +1,903 @@
 use std::{
-    path::{Path, PathBuf},
-    str::Chars,
+    borrow::Cow,
+    collections::HashMap,
+    fs::File,
+    io::{self, Read},
+    ops::Deref,
+    path::Path,
+    str::SplitWhitespace,
 };
 
-use crate::math::{Mesh, Tri};
-use glam::Vec3;
+use crate::math::{MeshBuilder, Model, Part, Surface, Vertex};
+use flate2::read::MultiGzDecoder;
+use glam::{Vec2, Vec3};
+use memmap2::Mmap;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
-#[derive(Clone, Copy)]
-pub enum MeshError {
-    InvalidMeshError,
-    FileNotFoundError,
-    UTF8Error,
+mod error;
+mod gltf;
+mod mtl;
+mod options;
+mod ply;
+mod stl;
+
+pub use error::{MeshError, MeshErrorKind};
+pub use gltf::load_gltf;
+pub use mtl::{load_mtl, Material};
+pub use options::{Format, Handedness, ImportOptions, ParseOptionError, UpAxis};
+pub use ply::load_ply;
+pub use stl::load_stl;
+
+/// Loads a model with the loader matching the file extension, using the default `ImportOptions`.
+/// Only .obj files are split into parts, other formats load as a single part.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Model, MeshError> {
+    load_with_options(path, &ImportOptions::default())
+}
+
+/// Loads a model like `load` and converts it into the renderer's space with `options`
+pub fn load_with_options<P: AsRef<Path>>(
+    path: P,
+    options: &ImportOptions,
+) -> Result<Model, MeshError> {
+    load_with_progress(path, options, |_, _| ())
+}
+
+/// Loads a model like `load_with_options`, calling `progress` with the number of bytes parsed so far
+/// and the size of the file. Only .obj files report progress while they are parsed.
+pub fn load_with_progress<P: AsRef<Path>>(
+    path: P,
+    options: &ImportOptions,
+    progress: impl FnMut(usize, usize),
+) -> Result<Model, MeshError> {
+    load_raw(path.as_ref(), options.format, progress).map(|model| options.apply(model))
+}
+
+/// Loads a model from a reader, like stdin, in the format given by `options`.
+/// Files referenced by the model are looked up relative to the working directory.
+pub fn load_from_reader<R: Read>(
+    mut reader: R,
+    options: &ImportOptions,
+) -> Result<Model, MeshError> {
+    let format = options.format.ok_or(MeshErrorKind::UnsupportedFormat)?;
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    let data = FileContents::Read(data).decompressed()?;
+    parse(&data, format, Path::new(""), |_, _| ()).map(|model| options.apply(model))
+}
+
+/// Loads a model in the coordinates of the file, like all `load_*` functions.
+/// Without a `format` it is detected from the extension.
+fn load_raw(
+    path: &Path,
+    format: Option<Format>,
+    progress: impl FnMut(usize, usize),
+) -> Result<Model, MeshError> {
+    let format = format
+        .or_else(|| Format::from_path(path))
+        .ok_or_else(|| MeshError::new(MeshErrorKind::UnsupportedFormat).in_file(path))?;
+    let contents = FileContents::open(path)
+        .and_then(FileContents::decompressed)
+        .map_err(|err| MeshError::from(err).in_file(path))?;
+    // Files referenced by the model are relative to it
+    let dir = path.parent().unwrap_or(Path::new(""));
+    parse(&contents, format, dir, progress).map_err(|err| err.in_file(path))
+}
+
+fn parse(
+    data: &[u8],
+    format: Format,
+    dir: &Path,
+    progress: impl FnMut(usize, usize),
+) -> Result<Model, MeshError> {
+    match format {
+        Format::Obj => parse_obj(data, dir, OBJ_CHUNK_LEN, progress),
+        Format::Stl => stl::parse_stl(data).map(Model::from),
+        Format::Ply => ply::parse_ply(data).map(Model::from),
+        Format::Gltf => gltf::parse_gltf(data, dir.to_path_buf()).map(Model::from),
+    }
+}
+
+/// Reads a text file, errors carry the path
+fn read_to_string(path: &Path) -> Result<String, MeshError> {
+    let file = std::fs::read(path).map_err(|err| MeshError::from(err).in_file(path))?;
+    String::from_utf8(file).map_err(|err| MeshError::from(err.utf8_error()).in_file(path))
+}
+
+/// Vertex data defined so far in an .obj file
+#[derive(Default)]
+struct ObjVertices {
+    positions: Vec<Vec3>,
+    /// Optional `v x y z r g b` colors in the 0..255 range, one per position
+    colors: Vec<Option<Vec3>>,
+    normals: Vec<Vec3>,
+    uvs: Vec<Vec2>,
+}
+
+/// Start of a part in the triangles of an .obj file
+struct ObjPart {
+    name: String,
+    material: Option<String>,
+    smoothing_group: Option<u32>,
+    start: usize,
+}
+
+/// Mesh being built from the faces of an .obj file
+#[derive(Default)]
+struct ObjMesh {
+    builder: MeshBuilder,
+    /// Vertex for every combination of indices and smoothing group used by the faces
+    vertices: HashMap<(usize, Option<usize>, Option<usize>, u32), u32>,
+    /// Surface for every material used by the faces
+    surfaces: HashMap<String, u32>,
+}
+
+impl ObjMesh {
+    /// Shared vertex for a face vertex in a smoothing group
+    fn vertex(&mut self, verts: &ObjVertices, corner: &FaceVertex, smoothing_group: u32) -> u32 {
+        // Vertices with a normal from the file look the same in every smoothing group
+        let group = if corner.vn.is_some() {
+            0
+        } else {
+            smoothing_group
+        };
+        let key = (corner.v, corner.vt, corner.vn, group);
+        *self.vertices.entry(key).or_insert_with(|| {
+            let vertex = obj_vertex(verts, corner).with_smoothing_group(group);
+            self.builder.add_vertex(vertex)
+        })
+    }
+
+    fn surface(&mut self, material: &Material) -> u32 {
+        *self
+            .surfaces
+            .entry(material.name.clone())
+            .or_insert_with(|| {
+                let mut surface = Surface::new(material.color());
+                if let Some(texture) = &material.texture {
+                    surface = surface.with_texture(texture.clone());
+                }
+                self.builder.add_surface(surface)
+            })
+    }
+}
+
+/// Size of the chunks an .obj file is split into to be parsed in parallel
+const OBJ_CHUNK_LEN: usize = 1 << 20;
+
+/// Loads an .obj file with a part for every object (`o`) and group (`g`)
+pub fn load_obj<P: AsRef<Path>>(path: P) -> Result<Model, MeshError> {
+    load_obj_with_progress(path, |_, _| ())
+}
+
+/// Loads an .obj file like `load_obj`, calling `progress` with the number of bytes parsed so far
+/// and the size of the file.
+/// The file is memory mapped when possible and parsed in parallel a few chunks at a time.
+/// Besides the file, the peak memory use is about the vertex data of the file plus twice the
+/// mesh while a file with several parts is split.
+pub fn load_obj_with_progress<P: AsRef<Path>>(
+    path: P,
+    progress: impl FnMut(usize, usize),
+) -> Result<Model, MeshError> {
+    let path = path.as_ref();
+    let contents = FileContents::open(path)
+        .and_then(FileContents::decompressed)
+        .map_err(|err| MeshError::from(err).in_file(path))?;
+    // Material libraries are referenced relative to the .obj file
+    let dir = path.parent().unwrap_or(Path::new(""));
+    parse_obj(&contents, dir, OBJ_CHUNK_LEN, progress).map_err(|err| err.in_file(path))
+}
+
+/// Contents of a file, memory mapped unless the file can't be mapped or is compressed
+enum FileContents {
+    Mapped(Mmap),
+    Read(Vec<u8>),
+}
+
+impl FileContents {
+    fn open(path: &Path) -> io::Result<Self> {
+        let mut file = File::open(path)?;
+        // SAFETY: the map is only read. Like with any memory map, the file must not be
+        // truncated by another process while it is being loaded.
+        match unsafe { Mmap::map(&file) } {
+            Ok(map) => Ok(Self::Mapped(map)),
+            Err(_) => {
+                let mut data = Vec::new();
+                file.read_to_end(&mut data)?;
+                Ok(Self::Read(data))
+            }
+        }
+    }
+
+    /// Decompresses gzip data, anything else is returned as is
+    fn decompressed(self) -> io::Result<Self> {
+        if !self.starts_with(&[0x1f, 0x8b]) {
+            return Ok(self);
+        }
+        let mut data = Vec::new();
+        MultiGzDecoder::new(&*self).read_to_end(&mut data)?;
+        Ok(Self::Read(data))
+    }
+}
+
+impl Deref for FileContents {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Mapped(map) => map,
+            Self::Read(data) => data,
+        }
+    }
+}
+
+/// Parses an .obj file in chunks of about `chunk_len` bytes, ending at line boundaries
+fn parse_obj(
+    data: &[u8],
+    dir: &Path,
+    chunk_len: usize,
+    mut progress: impl FnMut(usize, usize),
+) -> Result<Model, MeshError> {
+    let mut obj = ObjLoader::new(dir);
+    // Only a batch of chunks is parsed at a time to bound the memory used by the statements
+    let batch_len = chunk_len * rayon::current_num_threads();
+    let mut batch_start = 0;
+    let mut first_line = 1;
+
+    while batch_start < data.len() {
+        let batch_end = chunk_end(data, batch_start + batch_len);
+        let mut chunks = Vec::new();
+        let mut chunk_start = batch_start;
+        while chunk_start < batch_end {
+            let chunk_end = chunk_end(&data[..batch_end], chunk_start + chunk_len);
+            chunks.push(&data[chunk_start..chunk_end]);
+            chunk_start = chunk_end;
+        }
+
+        let chunks = chunks
+            .into_par_iter()
+            .map(parse_obj_chunk)
+            .collect::<Vec<_>>();
+        for chunk in chunks {
+            let chunk = chunk.map_err(|(line, err)| err.at_line(first_line + line - 1))?;
+            for (line, statement) in chunk.statements {
+                obj.statement(statement)
+                    .map_err(|err| err.at_line(first_line + line - 1))?;
+            }
+            first_line += chunk.line_count;
+        }
+
+        batch_start = batch_end;
+        progress(batch_start, data.len());
+    }
+
+    Ok(obj.finish())
+}
+
+/// End of the first line ending at or after `from` that isn't continued on the next line with `\`
+fn chunk_end(data: &[u8], from: usize) -> usize {
+    let mut pos = from;
+    while let Some(newline) = data
+        .get(pos..)
+        .and_then(|rest| rest.iter().position(|c| *c == b'\n'))
+    {
+        let end = pos + newline;
+        let start = data[..end]
+            .iter()
+            .rposition(|c| *c == b'\n')
+            .map_or(0, |idx| idx + 1);
+        let line = &data[start..end];
+        let line = line
+            .iter()
+            .position(|c| *c == b'#')
+            .map_or(line, |idx| &line[..idx]);
+        if !line.trim_ascii_end().ends_with(b"\\") {
+            return end + 1;
+        }
+        pos = end + 1;
+    }
+    data.len()
 }
 
-pub fn load_obj<P: AsRef<Path>>(path: P) -> Result<Mesh, MeshError> {
-    let mut verts = Vec::new();
-    let mut tris = Vec::new();
+/// Statement of an .obj file that is applied in file order
+enum ObjStatement<'a> {
+    Vertex(Vec3, Option<Vec3>),
+    Normal(Vec3),
+    Uv(Vec2),
+    /// Faces and statements that change the state, like `usemtl`, parsed when they are applied
+    Other(Cow<'a, str>),
+}
+
+/// Statements of a chunk of an .obj file
+struct ObjChunk<'a> {
+    /// Statements with the 1-based line number in the chunk they start on
+    statements: Vec<(usize, ObjStatement<'a>)>,
+    line_count: usize,
+}
+
+/// Parses the vertex data of a chunk, errors carry the 1-based line number in the chunk
+fn parse_obj_chunk(data: &[u8]) -> Result<ObjChunk<'_>, (usize, MeshError)> {
+    let count_lines = |data: &[u8]| data.iter().filter(|c| **c == b'\n').count();
+    let text = std::str::from_utf8(data)
+        .map_err(|err| (count_lines(&data[..err.valid_up_to()]) + 1, err.into()))?;
+
+    let mut statements = Vec::new();
+    for (line_num, line) in logical_lines(text) {
+        if let Some(statement) = parse_statement(line).map_err(|err| (line_num, err))? {
+            statements.push((line_num, statement));
+        }
+    }
+
+    Ok(ObjChunk {
+        statements,
+        line_count: count_lines(data),
+    })
+}
+
+/// Parses a line, unknown statements are skipped
+fn parse_statement(line: Cow<'_, str>) -> Result<Option<ObjStatement<'_>>, MeshError> {
+    let mut tokens = line.split_whitespace();
+    let statement = match tokens.next() {
+        Some("v") => {
+            let (position, color) = parse_vertex(tokens)?;
+            ObjStatement::Vertex(position, color)
+        }
+        Some("vn") => {
+            let normal = parse_vec3(&mut tokens)?;
+            if let Some(part) = tokens.next() {
+                return Err(MeshError::new(MeshErrorKind::TooManyCoordinates).with_token(part));
+            }
+            ObjStatement::Normal(normal)
+        }
+        Some("vt") => ObjStatement::Uv(parse_uv(tokens)?),
+        Some("f" | "o" | "g" | "s" | "mtllib" | "usemtl") => ObjStatement::Other(line),
+        _ => return Ok(None),
+    };
+    Ok(Some(statement))
+}
+
+/// State of an .obj file being loaded
+struct ObjLoader<'a> {
+    dir: &'a Path,
+    verts: ObjVertices,
+    mesh: ObjMesh,
+    materials: HashMap<String, Material>,
+    default_material: Material,
+    surface: u32,
+    material_name: Option<String>,
+    smoothing_group: Option<u32>,
+    parts: Vec<ObjPart>,
+}
 
-    let file = std::fs::read(path).map_err(|err| MeshError::FileNotFoundError)?;
-    let content = String::from_utf8(file).map_err(|err| MeshError::UTF8Error)?;
+impl<'a> ObjLoader<'a> {
+    fn new(dir: &'a Path) -> Self {
+        let mut mesh = ObjMesh::default();
+        let default_material = Material::new("");
+        let surface = mesh.surface(&default_material);
+        // Faces before the first `s` statement are smoothed together,
+        // `s off` or `s 0` gives flat faces
+        let smoothing_group = Some(0);
+        Self {
+            dir,
+            verts: ObjVertices::default(),
+            mesh,
+            materials: HashMap::new(),
+            default_material,
+            surface,
+            material_name: None,
+            smoothing_group,
+            parts: vec![ObjPart {
+                name: String::new(),
+                material: None,
+                smoothing_group,
+                start: 0,
+            }],
+        }
+    }
 
-    for line in content.lines() {
-        let mut parts = line.split(' ');
-        match parts.next() {
-            Some(t) => match t {
-                "v" => {
-                    add_vertex(&mut verts, parts)?;
+    fn statement(&mut self, statement: ObjStatement) -> Result<(), MeshError> {
+        let line = match statement {
+            ObjStatement::Vertex(position, color) => {
+                self.verts.positions.push(position);
+                self.verts.colors.push(color);
+                return Ok(());
+            }
+            ObjStatement::Normal(normal) => {
+                self.verts.normals.push(normal);
+                return Ok(());
+            }
+            ObjStatement::Uv(uv) => {
+                self.verts.uvs.push(uv);
+                return Ok(());
+            }
+            ObjStatement::Other(line) => line,
+        };
+
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("f") => {
+                add_face(
+                    &mut self.mesh,
+                    &self.verts,
+                    self.surface,
+                    self.smoothing_group,
+                    tokens,
+                )?;
+            }
+            Some("o" | "g") => {
+                let name = tokens.collect::<Vec<_>>().join(" ");
+                let tri_count = self.mesh.builder.tri_count();
+                let part = self.parts.last_mut().unwrap();
+                // Parts without faces are replaced, like a `g` right after an `o`
+                if part.start != tri_count {
+                    self.parts.push(ObjPart {
+                        name,
+                        material: self.material_name.clone(),
+                        smoothing_group: self.smoothing_group,
+                        start: tri_count,
+                    });
+                } else {
+                    part.name = name;
+                }
+            }
+            Some("s") => {
+                self.smoothing_group = match tokens.next() {
+                    Some("off") => None,
+                    Some(group) => match resolve_smoothing_group(group)? {
+                        0 => None,
+                        group => Some(group),
+                    },
+                    None => return Err(MeshErrorKind::MissingCoordinate.into()),
+                };
+                let part = self.parts.last_mut().unwrap();
+                if part.start == self.mesh.builder.tri_count() {
+                    part.smoothing_group = self.smoothing_group;
                 }
-                "f" => {
-                    add_face(&mut tris, &verts, parts)?;
+            }
+            Some("mtllib") => {
+                for lib in tokens {
+                    match load_mtl(self.dir.join(lib)) {
+                        Ok(lib) => self.materials.extend(lib),
+                        // A missing library shouldn't prevent the geometry from loading
+                        Err(MeshError {
+                            kind: MeshErrorKind::Io(err),
+                            ..
+                        }) if err.kind() == io::ErrorKind::NotFound => (),
+                        Err(err) => return Err(err),
+                    }
                 }
-                _ => (),
+            }
+            Some("usemtl") => {
+                self.material_name = tokens.next().map(str::to_string);
+                let material = self
+                    .material_name
+                    .as_ref()
+                    .and_then(|name| self.materials.get(name))
+                    .unwrap_or(&self.default_material);
+                self.surface = self.mesh.surface(material);
+                let part = self.parts.last_mut().unwrap();
+                if part.start == self.mesh.builder.tri_count() {
+                    part.material = self.material_name.clone();
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Model {
+        let Self {
+            verts,
+            mesh: ObjMesh {
+                builder, vertices, ..
             },
-            None => (),
+            parts,
+            ..
+        } = self;
+        // The vertex data of the file isn't needed anymore once the faces are built
+        drop((verts, vertices));
+        let tri_count = builder.tri_count();
+        let mesh = builder.build();
+
+        // Split the triangles into parts, dropping parts without faces
+        let mut ends = parts
+            .iter()
+            .skip(1)
+            .map(|part| part.start)
+            .collect::<Vec<_>>();
+        ends.push(tri_count);
+        let parts = match parts.len() {
+            // A file without objects or groups doesn't need to be split
+            1 => vec![Part {
+                name: String::new(),
+                material: parts[0].material.clone(),
+                smoothing_group: parts[0].smoothing_group,
+                mesh,
+            }],
+            _ => parts
+                .into_iter()
+                .zip(ends)
+                .filter(|(part, end)| part.start != *end)
+                .map(|(part, end)| Part {
+                    name: part.name,
+                    material: part.material,
+                    smoothing_group: part.smoothing_group,
+                    mesh: mesh.extract(part.start..end),
+                })
+                .collect(),
         };
+
+        Model::new(parts)
     }
+}
 
-    Ok(Mesh::new(tris))
+fn resolve_smoothing_group(group: &str) -> Result<u32, MeshError> {
+    group
+        .parse()
+        .map_err(|_| MeshError::new(MeshErrorKind::BadIndex).with_token(group))
+}
+
+/// Lines of an .obj file with comments removed and `\` continuations joined,
+/// along with the 1-based number of the line they start on
+fn logical_lines(content: &str) -> impl Iterator<Item = (usize, Cow<'_, str>)> {
+    fn strip_comment(line: &str) -> &str {
+        line.split_once('#').map_or(line, |(line, _)| line)
+    }
+    let mut lines = content.lines().enumerate();
+    std::iter::from_fn(move || {
+        let (idx, line) = lines.next()?;
+        let mut line = Cow::Borrowed(strip_comment(line));
+        while let Some(start) = line.trim_end().strip_suffix('\\') {
+            let mut joined = format!("{start} ");
+            if let Some((_, next)) = lines.next() {
+                joined.push_str(strip_comment(next));
+            }
+            line = Cow::Owned(joined);
+        }
+        Some((idx + 1, line))
+    })
 }
 
+/// Splits a polygon into a triangle fan
 fn add_face(
-    tris: &mut Vec<Tri>,
-    verts: &[Vec3],
-    mut parts: std::str::Split<char>,
+    mesh: &mut ObjMesh,
+    verts: &ObjVertices,
+    surface: u32,
+    smoothing_group: Option<u32>,
+    parts: SplitWhitespace,
 ) -> Result<(), MeshError> {
-    let mut collected_tris = Vec::new();
-
-    let v1 = parts.next().ok_or(MeshError::InvalidMeshError)?;
-    let v1: usize = v1
-        .parse::<usize>()
-        .map_err(|_| MeshError::InvalidMeshError)?
-        - 1;
-    let mut parts_peek = parts.peekable();
-    while let Some(idx) = parts_peek.next() {
-        if let Some(idx_2) = parts_peek.peek() {
-            let v2 = idx
-                .parse::<usize>()
-                .map_err(|_| MeshError::InvalidMeshError)?
-                - 1;
-            let v3 = idx_2
-                .parse::<usize>()
-                .map_err(|_| MeshError::InvalidMeshError)?
-                - 1;
-            collected_tris.push(Tri::new(
-                *verts.get(v1).ok_or(MeshError::InvalidMeshError)?,
-                *verts.get(v2).ok_or(MeshError::InvalidMeshError)?,
-                *verts.get(v3).ok_or(MeshError::InvalidMeshError)?,
-                Vec3::new(255., 255., 255.),
-            ));
-        }
-    }
-
-    if collected_tris.is_empty() {
-        return Err(MeshError::InvalidMeshError);
-    }
-
-    tris.append(&mut collected_tris);
+    let corners = parts
+        .map(|part| parse_face_vertex(part, verts))
+        .collect::<Result<Vec<_>, _>>()?;
+    if corners.len() < 3 {
+        return Err(MeshErrorKind::TooFewVertices.into());
+    }
+
+    for i in 1..corners.len() - 1 {
+        let tri = [&corners[0], &corners[i], &corners[i + 1]];
+        let has_normals = tri.iter().all(|corner| corner.vn.is_some());
+        let indices = match smoothing_group {
+            Some(group) => tri.map(|corner| mesh.vertex(verts, corner, group)),
+            None if has_normals => tri.map(|corner| mesh.vertex(verts, corner, 0)),
+            // Flat triangles get vertices of their own with the face normal
+            None => {
+                let [v0, v1, v2] = tri.map(|corner| verts.positions[corner.v]);
+                let normal = (v1 - v0).cross(v2 - v0).normalize_or_zero();
+                tri.map(|corner| {
+                    let vertex = obj_vertex(verts, corner).with_normal(normal);
+                    mesh.builder.add_vertex(vertex)
+                })
+            }
+        };
+        mesh.builder.add_tri(indices, surface);
+    }
 
     Ok(())
 }
 
-fn add_vertex(verts: &mut Vec<Vec3>, mut parts: std::str::Split<char>) -> Result<(), MeshError> {
+fn obj_vertex(verts: &ObjVertices, corner: &FaceVertex) -> Vertex {
+    let mut vertex = Vertex::new(verts.positions[corner.v]);
+    if let Some(vn) = corner.vn {
+        vertex = vertex.with_normal(verts.normals[vn]);
+    }
+    if let Some(vt) = corner.vt {
+        vertex = vertex.with_uv(verts.uvs[vt]);
+    }
+    if let Some(color) = verts.colors[corner.v] {
+        vertex = vertex.with_color(color);
+    }
+    vertex
+}
+
+/// 0-based indices of a face vertex, checked to be in range
+struct FaceVertex {
+    v: usize,
+    vt: Option<usize>,
+    vn: Option<usize>,
+}
+
+/// Parses a face vertex in any of the forms `v`, `v/vt`, `v//vn` or `v/vt/vn`
+fn parse_face_vertex(token: &str, verts: &ObjVertices) -> Result<FaceVertex, MeshError> {
+    let parse = || -> Result<FaceVertex, MeshError> {
+        let mut indices = token.split('/');
+        let v = indices.next().unwrap_or_default();
+        let v = resolve_index(v, verts.positions.len())?;
+
+        // Texture and normal indices are optional
+        let vt = match indices.next() {
+            Some(vt) if !vt.is_empty() => Some(resolve_index(vt, verts.uvs.len())?),
+            _ => None,
+        };
+        let vn = match indices.next() {
+            Some(vn) if !vn.is_empty() => Some(resolve_index(vn, verts.normals.len())?),
+            _ => None,
+        };
+        if indices.next().is_some() {
+            return Err(MeshErrorKind::TooManyCoordinates.into());
+        }
+
+        Ok(FaceVertex { v, vt, vn })
+    };
+    parse().map_err(|err| err.with_token(token))
+}
+
+/// Converts a 1-based OBJ index into a 0-based index.
+/// Negative indices are relative to the end of the `count` elements defined so far.
+fn resolve_index(idx: &str, count: usize) -> Result<usize, MeshError> {
+    let idx = idx.parse::<i64>().map_err(|_| MeshErrorKind::BadIndex)?;
+    let resolved = match idx {
+        0 => return Err(MeshErrorKind::BadIndex.into()),
+        1.. => Some(idx as usize - 1),
+        _ => count.checked_sub(idx.unsigned_abs() as usize),
+    };
+    match resolved {
+        Some(resolved) if resolved < count => Ok(resolved),
+        _ => Err(MeshErrorKind::IndexOutOfRange { index: idx, count }.into()),
+    }
+}
+
+/// Parses `x y z`
+fn parse_vec3(parts: &mut SplitWhitespace) -> Result<Vec3, MeshError> {
     // Coordinate 1
-    let c1 = parts.next().ok_or(MeshError::InvalidMeshError)?;
-    let c1: f32 = c1.parse().map_err(|_| MeshError::InvalidMeshError)?;
+    let c1 = parts.next().ok_or(MeshErrorKind::MissingCoordinate)?;
+    let c1: f32 = parse_float(c1)?;
 
     // Coordinate 2
-    let c2 = parts.next().ok_or(MeshError::InvalidMeshError)?;
-    let c2: f32 = -c2.parse().map_err(|_| MeshError::InvalidMeshError)?;
+    let c2 = parts.next().ok_or(MeshErrorKind::MissingCoordinate)?;
+    let c2: f32 = parse_float(c2)?;
 
     // Coordinate 3
-    let c3 = parts.next().ok_or(MeshError::InvalidMeshError)?;
-    let c3: f32 = c3.parse().map_err(|_| MeshError::InvalidMeshError)?;
+    let c3 = parts.next().ok_or(MeshErrorKind::MissingCoordinate)?;
+    let c3: f32 = parse_float(c3)?;
+
+    Ok(Vec3::new(c1, c2, c3))
+}
+
+/// Parses the position and color of a `v x y z`, `v x y z w` or `v x y z r g b` vertex.
+/// The weight `w` only matters for rational curves and is ignored.
+fn parse_vertex(mut parts: SplitWhitespace) -> Result<(Vec3, Option<Vec3>), MeshError> {
+    let position = parse_vec3(&mut parts)?;
+
+    let extra = parts.by_ref().take(3).collect::<Vec<_>>();
+    let color = match extra.as_slice() {
+        [] => None,
+        [w] => {
+            parse_float(w)?;
+            None
+        }
+        [r, g, b] => Some(Vec3::new(parse_float(r)?, parse_float(g)?, parse_float(b)?) * 255.),
+        _ => return Err(MeshErrorKind::MissingCoordinate.into()),
+    };
 
     // Too many coordinates
-    if parts.next() != None {
-        return Err(MeshError::InvalidMeshError);
+    if let Some(part) = parts.next() {
+        return Err(MeshError::new(MeshErrorKind::TooManyCoordinates).with_token(part));
     }
 
-    verts.push(Vec3::new(c1, c2, c3));
+    Ok((position, color))
+}
 
-    Ok(())
+fn parse_uv(mut parts: SplitWhitespace) -> Result<Vec2, MeshError> {
+    let u = parts.next().ok_or(MeshErrorKind::MissingCoordinate)?;
+    let u: f32 = parse_float(u)?;
+
+    // v and w are optional
+    let v = match parts.next() {
+        Some(v) => parse_float(v)?,
+        None => 0.,
+    };
+
+    Ok(Vec2::new(u, v))
+}
+
+fn parse_float(token: &str) -> Result<f32, MeshError> {
+    token
+        .parse()
+        .map_err(|_| MeshError::new(MeshErrorKind::BadFloat).with_token(token))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn verts(positions: usize, uvs: usize, normals: usize) -> ObjVertices {
+        ObjVertices {
+            positions: vec![Vec3::ZERO; positions],
+            colors: vec![None; positions],
+            normals: vec![Vec3::Z; normals],
+            uvs: vec![Vec2::ZERO; uvs],
+        }
+    }
+
+    fn face_vertex(token: &str) -> Result<(usize, Option<usize>, Option<usize>), MeshError> {
+        parse_face_vertex(token, &verts(4, 3, 2)).map(|corner| (corner.v, corner.vt, corner.vn))
+    }
+
+    #[test]
+    fn face_vertex_forms() {
+        assert_eq!(face_vertex("4").unwrap(), (3, None, None));
+        assert_eq!(face_vertex("1/3").unwrap(), (0, Some(2), None));
+        assert_eq!(face_vertex("2//1").unwrap(), (1, None, Some(0)));
+        assert_eq!(face_vertex("2/3/2").unwrap(), (1, Some(2), Some(1)));
+        assert_eq!(face_vertex("-1/-3/-2").unwrap(), (3, Some(0), Some(0)));
+
+        let err = face_vertex("1/2/2/1").unwrap_err();
+        assert!(matches!(err.kind, MeshErrorKind::TooManyCoordinates));
+        assert_eq!(err.token.as_deref(), Some("1/2/2/1"));
+        assert!(matches!(
+            face_vertex("1//3").unwrap_err().kind,
+            MeshErrorKind::IndexOutOfRange { index: 3, count: 2 }
+        ));
+    }
+
+    #[test]
+    fn indices() {
+        assert_eq!(resolve_index("1", 3).unwrap(), 0);
+        assert_eq!(resolve_index("3", 3).unwrap(), 2);
+        assert_eq!(resolve_index("-1", 3).unwrap(), 2);
+        assert_eq!(resolve_index("-3", 3).unwrap(), 0);
+        for idx in ["0", "x", "1.5", ""] {
+            let err = resolve_index(idx, 3).unwrap_err();
+            assert!(matches!(err.kind, MeshErrorKind::BadIndex), "{idx}");
+        }
+        for (idx, index) in [("4", 4), ("-4", -4)] {
+            let err = resolve_index(idx, 3).unwrap_err();
+            assert!(
+                matches!(err.kind, MeshErrorKind::IndexOutOfRange { index: i, count: 3 } if i == index),
+                "{idx}"
+            );
+        }
+    }
+
+    fn lines(content: &str) -> Vec<(usize, String)> {
+        logical_lines(content)
+            .map(|(line, text)| (line, text.into_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn comments_and_continuations() {
+        assert_eq!(
+            lines("v 1 2 3 # comment\n# only a comment\nf 1 \\\n 2 \\ # comment\n3\nvt 0 0"),
+            [
+                (1, "v 1 2 3 ".to_string()),
+                (2, String::new()),
+                (3, "f 1   2  3".to_string()),
+                (6, "vt 0 0".to_string()),
+            ]
+        );
+        // A continuation on the last line joins an empty line
+        assert_eq!(lines("f 1 2 3 \\"), [(1, "f 1 2 3  ".to_string())]);
+    }
+
+    fn vertex(line: &str) -> Result<(Vec3, Option<Vec3>), MeshError> {
+        parse_vertex(line.split_whitespace())
+    }
+
+    #[test]
+    fn vertex_forms() {
+        let position = Vec3::new(1., -2., 0.5);
+        assert_eq!(vertex("1 -2 0.5").unwrap(), (position, None));
+        assert_eq!(vertex("\t1   -2\t\t0.5  ").unwrap(), (position, None));
+        assert_eq!(vertex("1 -2 0.5 1.0").unwrap(), (position, None));
+        assert_eq!(
+            vertex("1 -2 0.5 1 0 0.5").unwrap(),
+            (position, Some(Vec3::new(255., 0., 127.5)))
+        );
+
+        assert!(matches!(
+            vertex("1 2").unwrap_err().kind,
+            MeshErrorKind::MissingCoordinate
+        ));
+        assert!(matches!(
+            vertex("1 2 3 1 0").unwrap_err().kind,
+            MeshErrorKind::MissingCoordinate
+        ));
+        assert!(matches!(
+            vertex("1 2 3 1 0 0 0").unwrap_err().kind,
+            MeshErrorKind::TooManyCoordinates
+        ));
+        let err = vertex("1 2 x").unwrap_err();
+        assert!(matches!(err.kind, MeshErrorKind::BadFloat));
+        assert_eq!(err.token.as_deref(), Some("x"));
+    }
+
+    #[test]
+    fn faces_in_a_file() {
+        let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\nvn 0 0 1\nf 1//1 2//1 3//1\nf -3 -1 -2\n";
+        let mesh = parse_obj(obj.as_bytes(), Path::new(""), OBJ_CHUNK_LEN, |_, _| ())
+            .unwrap()
+            .mesh();
+        assert_eq!(mesh.tri_count(), 2);
+        assert_eq!(mesh.corners(1), [Vec3::X, Vec3::new(1., 1., 0.), Vec3::Y]);
+
+        let err = parse_obj(
+            b"v 0 0 0\nv 1 0 0\nf 1 2 0\n",
+            Path::new(""),
+            OBJ_CHUNK_LEN,
+            |_, _| (),
+        )
+        .unwrap_err();
+        assert!(matches!(err.kind, MeshErrorKind::BadIndex));
+        assert_eq!(err.line, Some(3));
+    }
+
+    /// Parses `obj` with every chunk length up to its size, so statements span chunks and batches
+    fn parse_chunked(obj: &[u8]) -> impl Iterator<Item = Result<Model, MeshError>> + '_ {
+        (1..=obj.len()).map(|chunk_len| parse_obj(obj, Path::new(""), chunk_len, |_, _| ()))
+    }
+
+    #[test]
+    fn chunks() {
+        let obj =
+            b"v 0 0 0\nv 1 \\\n0 0\n# comment \\\nv 0 1 0\nf 1 \\\n 2 \\\n 3\nv 1 1 0\nf 2 4 3\n";
+        let expected = parse_obj(obj, Path::new(""), OBJ_CHUNK_LEN, |_, _| ())
+            .unwrap()
+            .mesh();
+        assert_eq!(expected.tri_count(), 2);
+        assert_eq!(expected.corners(0), [Vec3::ZERO, Vec3::X, Vec3::Y]);
+        for model in parse_chunked(obj) {
+            let mesh = model.unwrap().mesh();
+            assert_eq!(mesh.positions, expected.positions);
+            assert_eq!(mesh.indices, expected.indices);
+        }
+    }
+
+    #[test]
+    fn error_lines_in_chunks() {
+        // Errors in the vertex data are found while parsing the chunks, errors in faces later
+        let cases: [(&[u8], usize); 3] = [
+            (b"v 0 0 0\nv 1 \\\n0 0\n\nv 1 x 0\n", 5),
+            (b"v 0 0 0\nv 1 \\\n0 0\n\nf 1 2 \\\n 4\n", 5),
+            (b"v 0 0 0\n# \\\nv 1 0 0\n\xff\n", 4),
+        ];
+        for (obj, line) in cases {
+            for err in parse_chunked(obj) {
+                assert_eq!(err.unwrap_err().line, Some(line));
+            }
+        }
+        let err = parse_chunked(b"v 0 0 0\n\xff\n")
+            .last()
+            .unwrap()
+            .unwrap_err();
+        assert!(matches!(err.kind, MeshErrorKind::Utf8(_)));
+    }
+
+    #[test]
+    fn progress() {
+        let obj = b"v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n";
+        for chunk_len in [1, 8, OBJ_CHUNK_LEN] {
+            let mut calls = Vec::new();
+            parse_obj(obj, Path::new(""), chunk_len, |done, total| {
+                calls.push((done, total))
+            })
+            .unwrap();
+            assert_eq!(calls.last(), Some(&(obj.len(), obj.len())));
+            assert!(calls.windows(2).all(|pair| pair[0].0 < pair[1].0));
+            assert!(calls.iter().all(|(_, total)| *total == obj.len()));
+        }
+        // Nothing to parse, nothing to report
+        let mut calls = 0;
+        parse_obj(b"", Path::new(""), 1, |_, _| calls += 1).unwrap();
+        assert_eq!(calls, 0);
+    }
 }
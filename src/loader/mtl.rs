@@ -0,0 +1,139 @@
+use std::{collections::HashMap, io, path::Path, sync::Arc};
+
+use glam::Vec3;
+
+use super::{MeshError, MeshErrorKind};
+use crate::texture::{Texture, TextureError};
+
+/// Material from a .mtl file, colors are in the 0..1 range
+#[derive(Debug, Clone)]
+pub struct Material {
+    pub name: String,
+    /// Ambient color (Ka)
+    pub ambient: Vec3,
+    /// Diffuse color (Kd)
+    pub diffuse: Vec3,
+    /// Specular color (Ks)
+    pub specular: Vec3,
+    /// Specular exponent (Ns)
+    pub shininess: f32,
+    /// Opacity (d, or 1 - Tr)
+    pub dissolve: f32,
+    /// Diffuse texture (map_Kd)
+    pub texture: Option<Arc<Texture>>,
+}
+
+impl Material {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ambient: Vec3::ZERO,
+            diffuse: Vec3::ONE,
+            specular: Vec3::ZERO,
+            shininess: 0.,
+            dissolve: 1.,
+            texture: None,
+        }
+    }
+
+    /// Diffuse color scaled to the 0..255 range used by `Tri::color`
+    pub fn color(&self) -> Vec3 {
+        self.diffuse * 255.
+    }
+}
+
+/// Loads all materials in a .mtl file by name
+pub fn load_mtl<P: AsRef<Path>>(path: P) -> Result<HashMap<String, Material>, MeshError> {
+    let path = path.as_ref();
+    // Textures are referenced relative to the .mtl file
+    let dir = path.parent().unwrap_or(Path::new(""));
+
+    let content = super::read_to_string(path)?;
+
+    let mut materials = HashMap::new();
+    let mut current: Option<Material> = None;
+
+    for (line_idx, line) in content.lines().enumerate() {
+        let in_line = |err: MeshError| err.at_line(line_idx + 1).in_file(path);
+        let mut parts = line.split_whitespace();
+        let Some(t) = parts.next() else {
+            continue;
+        };
+
+        if t == "newmtl" {
+            let name = parts
+                .next()
+                .ok_or_else(|| in_line(MeshError::malformed("missing material name")))?;
+            if let Some(material) = current.replace(Material::new(name)) {
+                materials.insert(material.name.clone(), material);
+            }
+            continue;
+        }
+
+        // Statements before the first newmtl have nothing to apply to
+        let Some(material) = current.as_mut() else {
+            continue;
+        };
+        parse_statement(material, t, parts, dir).map_err(in_line)?;
+    }
+
+    if let Some(material) = current {
+        materials.insert(material.name.clone(), material);
+    }
+
+    Ok(materials)
+}
+
+fn parse_statement<'a>(
+    material: &mut Material,
+    t: &str,
+    mut parts: impl Iterator<Item = &'a str>,
+    dir: &Path,
+) -> Result<(), MeshError> {
+    match t {
+        "Ka" => material.ambient = parse_color(parts)?,
+        "Kd" => material.diffuse = parse_color(parts)?,
+        "Ks" => material.specular = parse_color(parts)?,
+        "Ns" => material.shininess = parse_float(parts.next())?,
+        "d" => material.dissolve = parse_float(parts.next())?,
+        "Tr" => material.dissolve = 1. - parse_float(parts.next())?,
+        "map_Kd" => {
+            // The file name comes after any texture options
+            let file = parts
+                .last()
+                .ok_or_else(|| MeshError::malformed("missing texture file name"))?;
+            let path = dir.join(file);
+            material.texture = match Texture::load(&path) {
+                Ok(texture) => Some(Arc::new(texture)),
+                // Missing or unsupported textures shouldn't prevent the geometry from loading
+                Err(TextureError::Io(err)) if err.kind() == io::ErrorKind::NotFound => None,
+                Err(TextureError::UnsupportedFormatError) => None,
+                Err(err) => {
+                    return Err(MeshError::new(MeshErrorKind::Texture(err))
+                        .with_token(&path.display().to_string()))
+                }
+            };
+        }
+        _ => (),
+    }
+    Ok(())
+}
+
+fn parse_float(part: Option<&str>) -> Result<f32, MeshError> {
+    let part = part.ok_or(MeshErrorKind::MissingCoordinate)?;
+    part.parse()
+        .map_err(|_| MeshError::new(MeshErrorKind::BadFloat).with_token(part))
+}
+
+/// Parses `r [g b]`, a single value sets all channels
+fn parse_color<'a>(mut parts: impl Iterator<Item = &'a str>) -> Result<Vec3, MeshError> {
+    let r = parse_float(parts.next())?;
+    match parts.next() {
+        Some(g) => {
+            let g = parse_float(Some(g))?;
+            let b = parse_float(parts.next())?;
+            Ok(Vec3::new(r, g, b))
+        }
+        None => Ok(Vec3::splat(r)),
+    }
+}
@@ -0,0 +1,199 @@
+use std::{fmt, path::Path, str::FromStr};
+
+use glam::{Mat3, Vec3};
+
+use crate::math::Model;
+
+/// Format of a model file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Obj,
+    Stl,
+    Ply,
+    /// .gltf or .glb, which are told apart by their content
+    Gltf,
+}
+
+impl Format {
+    /// Format matching the extension of a path, skipping a `.gz` extension
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let path = path.as_ref();
+        let path = match path.extension()?.to_str()? {
+            "gz" | "GZ" => Path::new(path.file_stem()?),
+            _ => path,
+        };
+        path.extension()?.to_str()?.parse().ok()
+    }
+}
+
+/// Axis pointing up in the coordinate system of a file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UpAxis {
+    X,
+    Y,
+    Z,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Handedness {
+    Right,
+    Left,
+}
+
+/// How a file is read and how its coordinates are converted into the renderer's space,
+/// where x points right and y points down
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImportOptions {
+    /// Format of the file, detected from the extension when `None`
+    pub format: Option<Format>,
+    pub up: UpAxis,
+    pub handedness: Handedness,
+    /// Uniform scale, e.g. 0.001 to convert millimetres to metres
+    pub scale: f32,
+    /// Moves the center of the bounding box to the origin
+    pub recenter: bool,
+}
+
+/// Right handed, Y up, unscaled and in place, which is what .obj and glTF files use
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            format: None,
+            up: UpAxis::Y,
+            handedness: Handedness::Right,
+            scale: 1.,
+            recenter: false,
+        }
+    }
+}
+
+impl ImportOptions {
+    pub fn with_format(self, format: Format) -> Self {
+        Self {
+            format: Some(format),
+            ..self
+        }
+    }
+
+    pub fn with_up(self, up: UpAxis) -> Self {
+        Self { up, ..self }
+    }
+
+    pub fn with_handedness(self, handedness: Handedness) -> Self {
+        Self { handedness, ..self }
+    }
+
+    pub fn with_scale(self, scale: f32) -> Self {
+        Self { scale, ..self }
+    }
+
+    pub fn with_recenter(self, recenter: bool) -> Self {
+        Self { recenter, ..self }
+    }
+
+    /// Linear transform from file coordinates into the renderer's space
+    pub fn transform(&self) -> Mat3 {
+        // Rotate the up axis onto Y, keeping the system right handed
+        let to_y_up = match self.up {
+            UpAxis::X => Mat3::from_cols(Vec3::Y, -Vec3::X, Vec3::Z),
+            UpAxis::Y => Mat3::IDENTITY,
+            UpAxis::Z => Mat3::from_cols(Vec3::X, -Vec3::Z, Vec3::Y),
+        };
+        let to_right_handed = match self.handedness {
+            Handedness::Right => Mat3::IDENTITY,
+            Handedness::Left => Mat3::from_diagonal(Vec3::new(1., 1., -1.)),
+        };
+        // The screen's Y points down
+        let to_screen = Mat3::from_diagonal(Vec3::new(1., -1., 1.));
+        Mat3::from_diagonal(Vec3::splat(self.scale)) * to_screen * to_right_handed * to_y_up
+    }
+
+    /// Converts a model loaded in file coordinates, in place
+    pub fn apply(&self, mut model: Model) -> Model {
+        model.transform(self.transform(), Vec3::ZERO);
+        if let (Some((min, max)), true) = (model.bounds(), self.recenter) {
+            model.transform(Mat3::IDENTITY, -(min + max) / 2.);
+        }
+        model
+    }
+}
+
+impl FromStr for Format {
+    type Err = ParseOptionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "obj" => Ok(Format::Obj),
+            "stl" => Ok(Format::Stl),
+            "ply" => Ok(Format::Ply),
+            "gltf" | "glb" => Ok(Format::Gltf),
+            _ => Err(ParseOptionError("expected obj, stl, ply, gltf or glb")),
+        }
+    }
+}
+
+impl FromStr for UpAxis {
+    type Err = ParseOptionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "x" => Ok(UpAxis::X),
+            "y" => Ok(UpAxis::Y),
+            "z" => Ok(UpAxis::Z),
+            _ => Err(ParseOptionError("expected x, y or z")),
+        }
+    }
+}
+
+impl FromStr for Handedness {
+    type Err = ParseOptionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "right" => Ok(Handedness::Right),
+            "left" => Ok(Handedness::Left),
+            _ => Err(ParseOptionError("expected right or left")),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ParseOptionError(pub(crate) &'static str);
+
+impl fmt::Display for ParseOptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ParseOptionError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{Mesh, MeshBuilder, Vertex};
+
+    fn points(positions: &[Vec3]) -> Mesh {
+        let mut builder = MeshBuilder::new();
+        for position in positions {
+            builder.add_vertex(Vertex::new(*position));
+        }
+        builder.build()
+    }
+
+    #[test]
+    fn apply() {
+        let model = Model::from(points(&[Vec3::ZERO, Vec3::new(2., 0., 4.)]));
+        let options = ImportOptions::default().with_up(UpAxis::Z).with_scale(0.5);
+        let recentered = options.with_recenter(true).apply(model.clone());
+        // Z up becomes Y up, which points down on the screen
+        assert_eq!(
+            options.apply(model).bounds(),
+            Some((Vec3::new(0., -2., 0.), Vec3::new(1., 0., 0.)))
+        );
+        assert_eq!(
+            recentered.bounds(),
+            Some((Vec3::new(-0.5, -1., 0.), Vec3::new(0.5, 1., 0.)))
+        );
+    }
+}
@@ -0,0 +1,454 @@
+use std::{collections::HashMap, path::Path};
+
+use glam::Vec3;
+
+use super::{MeshError, MeshErrorKind};
+use crate::math::{Mesh, MeshBuilder, Surface, Vertex};
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Result<Self, MeshError> {
+        Ok(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return Err(MeshError::malformed("unknown property type").with_token(name)),
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    /// Scale that brings a color channel of this type into the 0..255 range
+    fn color_scale(self) -> f32 {
+        match self {
+            Scalar::F32 | Scalar::F64 => 255.,
+            Scalar::U16 | Scalar::I16 => 255. / u16::MAX as f32,
+            _ => 1.,
+        }
+    }
+}
+
+enum Property {
+    Scalar {
+        name: String,
+        ty: Scalar,
+    },
+    List {
+        name: String,
+        count: Scalar,
+        ty: Scalar,
+    },
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    /// Fewest bytes an entry can take, every ASCII value is at least one character
+    fn min_size(&self, format: Format) -> usize {
+        self.properties
+            .iter()
+            .map(|property| match (format, property) {
+                (Format::Ascii, _) => 1,
+                (_, Property::Scalar { ty, .. }) => ty.size(),
+                (_, Property::List { count, .. }) => count.size(),
+            })
+            .sum()
+    }
+}
+
+/// Loads an ASCII or binary (little or big endian) .ply file.
+/// Vertex colors are interpolated over the faces and multiplied with the colors of the faces.
+pub fn load_ply<P: AsRef<Path>>(path: P) -> Result<Mesh, MeshError> {
+    let path = path.as_ref();
+    let file = std::fs::read(path).map_err(|err| MeshError::from(err).in_file(path))?;
+    parse_ply(&file).map_err(|err| err.in_file(path))
+}
+
+pub(super) fn parse_ply(file: &[u8]) -> Result<Mesh, MeshError> {
+    let header_end = file
+        .windows(b"end_header".len())
+        .position(|w| w == b"end_header")
+        .ok_or_else(|| MeshError::malformed("missing `end_header`"))?;
+    // The body starts after the line ending of `end_header`
+    let body_start = file[header_end..]
+        .iter()
+        .position(|c| *c == b'\n')
+        .map(|idx| header_end + idx + 1)
+        .unwrap_or(file.len());
+    let header = std::str::from_utf8(&file[..header_end])?;
+    let (format, elements) = parse_header(header)?;
+
+    // Every entry of an element takes at least a byte per property, which bounds the counts
+    // before anything is read. Elements without properties take nothing and are skipped.
+    let body_len = file.len() - body_start;
+    let mut needed = 0usize;
+    for element in &elements {
+        needed = element
+            .count
+            .checked_mul(element.min_size(format))
+            .and_then(|size| needed.checked_add(size))
+            .filter(|needed| *needed <= body_len)
+            .ok_or_else(|| {
+                MeshError::new(MeshErrorKind::UnexpectedEof).with_token(&element.name)
+            })?;
+    }
+
+    let mut body = match format {
+        Format::Ascii => {
+            let first_line = header.lines().count() + 2;
+            Body::Ascii(Box::new(
+                std::str::from_utf8(&file[body_start..])?
+                    .lines()
+                    .enumerate()
+                    .flat_map(move |(idx, line)| {
+                        line.split_ascii_whitespace()
+                            .map(move |token| (first_line + idx, token))
+                    }),
+            ))
+        }
+        _ => Body::Binary {
+            data: &file[body_start..],
+            pos: 0,
+            big_endian: format == Format::BinaryBigEndian,
+        },
+    };
+
+    let mut builder = MeshBuilder::new();
+    let white = Vec3::new(255., 255., 255.);
+    // Surface for every face color
+    let mut surfaces = HashMap::new();
+
+    for element in elements
+        .iter()
+        .filter(|element| !element.properties.is_empty())
+    {
+        for _ in 0..element.count {
+            let mut position = Vec3::ZERO;
+            let mut normal = None::<Vec3>;
+            let mut color = None::<Vec3>;
+            let mut indices = Vec::new();
+
+            for property in &element.properties {
+                match property {
+                    Property::Scalar { name, ty } => {
+                        let value = body.read(*ty)?;
+                        let value_f32 = value as f32;
+                        let channel = value_f32 * ty.color_scale();
+                        match name.as_str() {
+                            "x" => position.x = value_f32,
+                            "y" => position.y = value_f32,
+                            "z" => position.z = value_f32,
+                            "nx" => normal.get_or_insert(Vec3::ZERO).x = value_f32,
+                            "ny" => normal.get_or_insert(Vec3::ZERO).y = value_f32,
+                            "nz" => normal.get_or_insert(Vec3::ZERO).z = value_f32,
+                            "red" | "r" | "diffuse_red" => {
+                                color.get_or_insert(Vec3::ZERO).x = channel
+                            }
+                            "green" | "g" | "diffuse_green" => {
+                                color.get_or_insert(Vec3::ZERO).y = channel
+                            }
+                            "blue" | "b" | "diffuse_blue" => {
+                                color.get_or_insert(Vec3::ZERO).z = channel
+                            }
+                            _ => (),
+                        }
+                    }
+                    Property::List { name, count, ty } => {
+                        let count = integer(body.read(*count)?)?;
+                        let is_indices = name == "vertex_indices" || name == "vertex_index";
+                        for _ in 0..count {
+                            let value = body.read(*ty)?;
+                            if is_indices {
+                                indices.push(integer(value)?);
+                            }
+                        }
+                    }
+                }
+            }
+
+            match element.name.as_str() {
+                "vertex" => {
+                    let mut vertex = Vertex::new(position);
+                    vertex.normal = normal;
+                    vertex.color = color;
+                    builder.add_vertex(vertex);
+                }
+                "face" => {
+                    let color = color.unwrap_or(white);
+                    let surface = *surfaces
+                        .entry(color.to_array().map(f32::to_bits))
+                        .or_insert_with(|| builder.add_surface(Surface::new(color)));
+                    add_face(&mut builder, &indices, surface)?;
+                }
+                _ => (),
+            }
+        }
+    }
+
+    Ok(builder.build())
+}
+
+fn parse_header(header: &str) -> Result<(Format, Vec<Element>), MeshError> {
+    let mut lines = header.lines();
+    if lines.next().map(str::trim) != Some("ply") {
+        return Err(MeshError::malformed("missing `ply` magic number").at_line(1));
+    }
+
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for (line_idx, line) in lines.enumerate() {
+        parse_header_line(line, &mut format, &mut elements)
+            .map_err(|err| err.at_line(line_idx + 2))?;
+    }
+
+    let format = format.ok_or_else(|| MeshError::malformed("missing `format` line"))?;
+    Ok((format, elements))
+}
+
+fn parse_header_line(
+    line: &str,
+    format: &mut Option<Format>,
+    elements: &mut Vec<Element>,
+) -> Result<(), MeshError> {
+    let missing = || MeshError::malformed("incomplete header line");
+    let mut parts = line.split_whitespace();
+    match parts.next() {
+        Some("format") => {
+            *format = Some(match parts.next() {
+                Some("ascii") => Format::Ascii,
+                Some("binary_little_endian") => Format::BinaryLittleEndian,
+                Some("binary_big_endian") => Format::BinaryBigEndian,
+                Some(token) => return Err(MeshError::malformed("unknown format").with_token(token)),
+                None => return Err(missing()),
+            });
+        }
+        Some("element") => {
+            let name = parts.next().ok_or_else(missing)?;
+            let count = parts.next().ok_or_else(missing)?;
+            let count = count
+                .parse()
+                .map_err(|_| MeshError::new(MeshErrorKind::BadIndex).with_token(count))?;
+            elements.push(Element {
+                name: name.to_string(),
+                count,
+                properties: Vec::new(),
+            });
+        }
+        Some("property") => {
+            let element = elements
+                .last_mut()
+                .ok_or_else(|| MeshError::malformed("property before the first element"))?;
+            let ty = parts.next().ok_or_else(missing)?;
+            let property = if ty == "list" {
+                let count = Scalar::parse(parts.next().ok_or_else(missing)?)?;
+                let ty = Scalar::parse(parts.next().ok_or_else(missing)?)?;
+                let name = parts.next().ok_or_else(missing)?;
+                Property::List {
+                    name: name.to_string(),
+                    count,
+                    ty,
+                }
+            } else {
+                let name = parts.next().ok_or_else(missing)?;
+                Property::Scalar {
+                    name: name.to_string(),
+                    ty: Scalar::parse(ty)?,
+                }
+            };
+            element.properties.push(property);
+        }
+        _ => (),
+    }
+    Ok(())
+}
+
+/// List counts and vertex indices, which have to be non-negative integers even when stored as floats
+fn integer(value: f64) -> Result<usize, MeshError> {
+    if value >= 0. && value.fract() == 0. {
+        Ok(value as usize)
+    } else {
+        Err(MeshError::new(MeshErrorKind::BadIndex).with_token(&value.to_string()))
+    }
+}
+
+/// Splits a polygon into a triangle fan like .obj faces
+fn add_face(builder: &mut MeshBuilder, indices: &[usize], surface: u32) -> Result<(), MeshError> {
+    if indices.len() < 3 {
+        return Err(MeshErrorKind::TooFewVertices.into());
+    }
+    let count = builder.vertex_count();
+    if let Some(idx) = indices.iter().find(|idx| **idx >= count) {
+        return Err(MeshErrorKind::IndexOutOfRange {
+            index: *idx as i64,
+            count,
+        }
+        .into());
+    }
+
+    for i in 1..indices.len() - 1 {
+        let corners = [indices[0], indices[i], indices[i + 1]];
+        builder.add_tri(corners.map(|idx| idx as u32), surface);
+    }
+
+    Ok(())
+}
+
+enum Body<'a> {
+    /// Tokens with their line numbers
+    Ascii(Box<dyn Iterator<Item = (usize, &'a str)> + 'a>),
+    Binary {
+        data: &'a [u8],
+        pos: usize,
+        big_endian: bool,
+    },
+}
+
+impl Body<'_> {
+    fn read(&mut self, ty: Scalar) -> Result<f64, MeshError> {
+        match self {
+            Body::Ascii(tokens) => {
+                let (line, token) = tokens.next().ok_or(MeshErrorKind::UnexpectedEof)?;
+                token.parse().map_err(|_| {
+                    MeshError::new(MeshErrorKind::BadFloat)
+                        .at_line(line)
+                        .with_token(token)
+                })
+            }
+            Body::Binary {
+                data,
+                pos,
+                big_endian,
+            } => {
+                let mut bytes = [0u8; 8];
+                let size = ty.size();
+                bytes[..size].copy_from_slice(
+                    data.get(*pos..*pos + size)
+                        .ok_or(MeshErrorKind::UnexpectedEof)?,
+                );
+                *pos += size;
+                if *big_endian {
+                    bytes[..size].reverse();
+                }
+                let b = bytes;
+                Ok(match ty {
+                    Scalar::I8 => b[0] as i8 as f64,
+                    Scalar::U8 => b[0] as f64,
+                    Scalar::I16 => i16::from_le_bytes([b[0], b[1]]) as f64,
+                    Scalar::U16 => u16::from_le_bytes([b[0], b[1]]) as f64,
+                    Scalar::I32 => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                    Scalar::U32 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                    Scalar::F32 => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                    Scalar::F64 => f64::from_le_bytes(b),
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\nelement face 1\nproperty list uchar float vertex_indices\nend_header\n0 0 0\n1 0 0\n0 1 0\n";
+
+    fn parse(faces: &str) -> Result<Mesh, MeshError> {
+        parse_ply(format!("{HEADER}{faces}").as_bytes())
+    }
+
+    #[test]
+    fn ascii_triangle() {
+        let mesh = parse("3 0 1 2\n").unwrap();
+        assert_eq!(mesh.tri_count(), 1);
+        assert_eq!(mesh.indices, [[0, 1, 2]]);
+    }
+
+    #[test]
+    fn rejects_bad_indices() {
+        for faces in [
+            "3 0 1 -1\n",
+            "3 0 1 1.5\n",
+            "3 0 1 nan\n",
+            "-3 0 1 2\n",
+            "3 0 1 inf\n",
+        ] {
+            let err = parse(faces).unwrap_err();
+            assert!(
+                matches!(err.kind, MeshErrorKind::BadIndex),
+                "{faces}: {err}"
+            );
+        }
+        assert!(matches!(
+            parse("3 0 1 3\n").unwrap_err().kind,
+            MeshErrorKind::IndexOutOfRange { index: 3, count: 3 }
+        ));
+    }
+
+    #[test]
+    fn element_counts() {
+        // Elements without properties are skipped however many entries they claim
+        let junk = HEADER.replace(
+            "end_header",
+            "element junk 18446744073709551615\nend_header",
+        );
+        assert_eq!(
+            parse_ply(format!("{junk}3 0 1 2\n").as_bytes())
+                .unwrap()
+                .tri_count(),
+            1
+        );
+
+        for count in ["1000000000", "18446744073709551615"] {
+            let header = HEADER.replace("element face 1", &format!("element face {count}"));
+            let err = parse_ply(format!("{header}3 0 1 2\n").as_bytes()).unwrap_err();
+            assert!(matches!(err.kind, MeshErrorKind::UnexpectedEof), "{err}");
+        }
+
+        let binary = "ply\nformat binary_little_endian 1.0\nelement vertex 4294967296\nproperty float x\nend_header\n\0\0\0\0";
+        assert!(matches!(
+            parse_ply(binary.as_bytes()).unwrap_err().kind,
+            MeshErrorKind::UnexpectedEof
+        ));
+    }
+
+    #[test]
+    fn truncated_body() {
+        assert!(matches!(
+            parse("3 0 1\n").unwrap_err().kind,
+            MeshErrorKind::UnexpectedEof
+        ));
+    }
+}
@@ -0,0 +1,173 @@
+use std::{collections::HashMap, path::Path};
+
+use glam::Vec3;
+
+use super::{MeshError, MeshErrorKind};
+use crate::math::{Mesh, MeshBuilder, Surface, Vertex};
+
+const HEADER_LEN: usize = 80;
+const FACET_LEN: usize = 50;
+
+/// Loads an ASCII or binary .stl file, the encoding is detected from the content
+pub fn load_stl<P: AsRef<Path>>(path: P) -> Result<Mesh, MeshError> {
+    let path = path.as_ref();
+    let file = std::fs::read(path).map_err(|err| MeshError::from(err).in_file(path))?;
+    parse_stl(&file).map_err(|err| err.in_file(path))
+}
+
+pub(super) fn parse_stl(file: &[u8]) -> Result<Mesh, MeshError> {
+    if is_binary(file) {
+        parse_binary(file)
+    } else {
+        std::str::from_utf8(file)
+            .map_err(MeshError::from)
+            .and_then(parse_ascii)
+    }
+}
+
+/// ASCII files start with `solid`, but so do some binary headers,
+/// so a file whose size matches its facet count is treated as binary
+fn is_binary(file: &[u8]) -> bool {
+    if let Some(count) = file.get(HEADER_LEN..HEADER_LEN + 4) {
+        let count = u32::from_le_bytes(count.try_into().unwrap()) as usize;
+        if file.len() == HEADER_LEN + 4 + count * FACET_LEN {
+            return true;
+        }
+    }
+    !file.trim_ascii_start().starts_with(b"solid")
+}
+
+fn parse_binary(file: &[u8]) -> Result<Mesh, MeshError> {
+    let header = file.get(..HEADER_LEN).ok_or(MeshErrorKind::UnexpectedEof)?;
+    let facets = file
+        .get(HEADER_LEN + 4..)
+        .ok_or(MeshErrorKind::UnexpectedEof)?;
+    let count = u32::from_le_bytes(file[HEADER_LEN..HEADER_LEN + 4].try_into().unwrap()) as usize;
+    if facets.len() < count * FACET_LEN {
+        return Err(MeshError::malformed(format!(
+            "header declares {count} facets but the file only holds {}",
+            facets.len() / FACET_LEN
+        )));
+    }
+
+    // Materialise Magics stores a default color in the header as `COLOR=` followed by RGBA
+    let magics_color = header
+        .windows(6)
+        .position(|w| w == b"COLOR=")
+        .and_then(|idx| header.get(idx + 6..idx + 9))
+        .map(|c| Vec3::new(c[0] as f32, c[1] as f32, c[2] as f32));
+
+    let mut builder = MeshBuilder::new();
+    let mut surfaces = HashMap::new();
+    for facet in facets.chunks_exact(FACET_LEN).take(count) {
+        let vec = |offset: usize| {
+            let f = |i: usize| {
+                f32::from_le_bytes(
+                    facet[offset + i * 4..offset + i * 4 + 4]
+                        .try_into()
+                        .unwrap(),
+                )
+            };
+            Vec3::new(f(0), f(1), f(2))
+        };
+        let attribute = u16::from_le_bytes([facet[48], facet[49]]);
+        let color = match magics_color {
+            Some(default) => magics_face_color(attribute).unwrap_or(default),
+            None => viscam_face_color(attribute).unwrap_or(Vec3::new(255., 255., 255.)),
+        };
+        let surface = *surfaces
+            .entry(attribute)
+            .or_insert_with(|| builder.add_surface(Surface::new(color)));
+        add_facet(&mut builder, vec(0), [vec(12), vec(24), vec(36)], surface);
+    }
+
+    Ok(builder.build())
+}
+
+/// Unpacks a 5 bit per channel color
+fn unpack_color(bits: u16) -> [f32; 3] {
+    [0, 5, 10].map(|shift| ((bits >> shift) & 0x1f) as f32 * 255. / 31.)
+}
+
+/// VisCAM / SolidView colors are BGR and only valid when bit 15 is set
+fn viscam_face_color(attribute: u16) -> Option<Vec3> {
+    let [b, g, r] = unpack_color(attribute);
+    (attribute & 0x8000 != 0).then_some(Vec3::new(r, g, b))
+}
+
+/// Magics colors are RGB and only valid when bit 15 is cleared
+fn magics_face_color(attribute: u16) -> Option<Vec3> {
+    let [r, g, b] = unpack_color(attribute);
+    (attribute & 0x8000 == 0).then_some(Vec3::new(r, g, b))
+}
+
+fn parse_ascii(content: &str) -> Result<Mesh, MeshError> {
+    let mut builder = MeshBuilder::new();
+    let surface = builder.add_surface(Surface::new(Vec3::new(255., 255., 255.)));
+    let mut tokens = content
+        .lines()
+        .enumerate()
+        .flat_map(|(idx, line)| line.split_whitespace().map(move |token| (idx + 1, token)));
+
+    let mut normal = Vec3::ZERO;
+    let mut verts = Vec::with_capacity(3);
+    while let Some((line, token)) = tokens.next() {
+        let in_line = |err: MeshError| err.at_line(line);
+        match token {
+            "facet" => {
+                match tokens.next() {
+                    Some((_, "normal")) => (),
+                    Some((line, token)) => {
+                        return Err(MeshError::malformed("expected `normal` after `facet`")
+                            .at_line(line)
+                            .with_token(token))
+                    }
+                    None => return Err(in_line(MeshErrorKind::UnexpectedEof.into())),
+                }
+                normal = parse_vec(&mut tokens).map_err(in_line)?;
+                verts.clear();
+            }
+            "vertex" => verts.push(parse_vec(&mut tokens).map_err(in_line)?),
+            "endloop" => {
+                if verts.len() < 3 {
+                    return Err(in_line(MeshErrorKind::TooFewVertices.into()));
+                }
+                // Polygons are split into a triangle fan like .obj faces
+                for i in 1..verts.len() - 1 {
+                    add_facet(
+                        &mut builder,
+                        normal,
+                        [verts[0], verts[i], verts[i + 1]],
+                        surface,
+                    );
+                }
+            }
+            _ => (),
+        }
+    }
+
+    Ok(builder.build())
+}
+
+fn parse_vec<'a>(tokens: &mut impl Iterator<Item = (usize, &'a str)>) -> Result<Vec3, MeshError> {
+    let mut c = [0f32; 3];
+    for c in c.iter_mut() {
+        let (line, token) = tokens.next().ok_or(MeshErrorKind::MissingCoordinate)?;
+        *c = token.parse().map_err(|_| {
+            MeshError::new(MeshErrorKind::BadFloat)
+                .at_line(line)
+                .with_token(token)
+        })?;
+    }
+    Ok(Vec3::new(c[0], c[1], c[2]))
+}
+
+/// Adds a flat triangle using the facet normal, unless the file left it zeroed
+fn add_facet(builder: &mut MeshBuilder, normal: Vec3, corners: [Vec3; 3], surface: u32) {
+    let [v0, v1, v2] = corners;
+    let normal = normal
+        .try_normalize()
+        .unwrap_or_else(|| (v1 - v0).cross(v2 - v0).normalize_or_zero());
+    let indices = corners.map(|v| builder.add_vertex(Vertex::new(v).with_normal(normal)));
+    builder.add_tri(indices, surface);
+}
@@ -1,174 +1,532 @@
 use std::{
-    env,
     error::Error,
-    f32::consts::PI,
-    io,
-    ops::Add,
-    sync::Arc,
-    thread::{self, JoinHandle},
-    time::Duration,
+    io::{self, IsTerminal, Write},
+    path::Path,
+    time::{Duration, SystemTime},
 };
 
-use clap::Parser;
-use crossterm::event::{
-    self, DisableMouseCapture, Event, KeyCode, KeyEvent, ModifierKeyCode, MouseEvent,
+use clap::{Parser, Subcommand};
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent},
+    terminal,
+};
+use glam::{Quat, Vec2, Vec3};
+use terminal_renderer::{
+    exporter,
+    loader::{Format, Handedness, ImportOptions, MeshError, UpAxis},
+    math::Model,
+    recorder::Recorder,
+    renderer::{self, Camera, Framebuffer, Projection, Renderer, View},
 };
-use glam::{Vec2, Vec3};
-use terminal_renderer::math::{Octree, Rotation};
 
 #[derive(Parser, Debug)]
-#[command(version, about, long_about=None)]
+#[command(version, about, long_about=None, args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
-    /// Path to the .obj file
-    #[arg(short, long)]
-    path: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Path to the .obj, .stl, .ply, .gltf or .glb file, which may be gzip compressed, or - for stdin
+    #[arg(short, long, required = true)]
+    path: Option<String>,
 
     /// Option to list the number of triangles instead of rendering
     #[arg(short)]
     count_tris: bool,
 
-    /// Characters to use for different light levels [low..high]
+    /// Characters to use for different light levels [low..high], a default ramp is used if none are given
     #[arg(long, num_args=0..)]
-    chars: Vec<char>,
+    chars: Option<Vec<char>>,
 
     /// Enables octree optimisation
     #[arg(short)]
     octree: bool,
+
+    /// Lists the parts (.obj objects and groups) of the model instead of rendering
+    #[arg(long)]
+    list_parts: bool,
+
+    /// Hides the parts with this name, can be given several times
+    #[arg(long, value_name = "PART")]
+    hide: Vec<String>,
+
+    /// Only shows the parts with this name, can be given several times
+    #[arg(long, value_name = "PART", conflicts_with = "hide")]
+    only: Vec<String>,
+
+    /// Draws the parts with this name in a color given as hex RRGGBB, can be given several times
+    #[arg(long, value_name = "PART=RRGGBB", value_parser = parse_part_color)]
+    color: Vec<(String, Vec3)>,
+
+    /// Reloads the model when the file changes, keeping the camera
+    #[arg(long)]
+    watch: bool,
+
+    /// Field of view across the shorter side of the terminal, in degrees
+    #[arg(long, default_value_t = renderer::DEFAULT_FOV, value_parser = parse_fov)]
+    fov: f32,
+
+    /// Starts with an orthographic projection instead of a perspective one (toggled with p)
+    #[arg(long)]
+    ortho: bool,
+
+    /// Direction to look at the model from [front, top, side, iso] (keys 1 to 4)
+    #[arg(long, default_value = "front")]
+    view: View,
+
+    /// Height of a terminal cell divided by its width, for fonts with unusual proportions
+    #[arg(long, default_value_t = renderer::DEFAULT_CELL_ASPECT, value_parser = parse_cell_aspect)]
+    cell_aspect: f32,
+
+    /// Largest difference of a color channel [0..255] that doesn't redraw a cell, higher values send less over slow connections
+    #[arg(long, value_name = "N", default_value_t = renderer::DEFAULT_COLOR_THRESHOLD)]
+    color_threshold: u8,
+
+    /// Renders a single frame into this file instead of opening the viewer, as a .png or .ppm image
+    /// or as text with escape codes (.ans, .txt or - for stdout). There is no short form, -o enables the octree
+    #[arg(long, value_name = "FILE", conflicts_with = "watch")]
+    output: Option<String>,
+
+    /// Size of the frame written with --output, in pixels for images and in columns and rows for text
+    /// [default: 800x600 for images, 80x24 for text]
+    #[arg(long, value_name = "WxH", value_parser = parse_size, requires = "output")]
+    size: Option<(usize, usize)>,
+
+    /// Records the session as an asciicast v2 (.cast) or animated .gif file
+    #[arg(long, value_name = "FILE", conflicts_with = "output")]
+    record: Option<String>,
+
+    #[command(flatten)]
+    import: ImportArgs,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Converts a model into .obj, .stl or .ply (chosen by the extension of OUTPUT), with Y up and right handed axes
+    Convert {
+        /// The .obj, .stl, .ply, .gltf or .glb file to convert, or - for stdin
+        input: String,
+
+        /// The file to write
+        output: String,
+
+        #[command(flatten)]
+        import: ImportArgs,
+    },
+}
+
+#[derive(clap::Args, Debug)]
+struct ImportArgs {
+    /// Format of the file [obj, stl, ply, gltf, glb], detected from the extension by default
+    #[arg(long)]
+    format: Option<Format>,
+
+    /// Axis pointing up in the file [x, y, z]
+    #[arg(long, default_value = "y")]
+    up: UpAxis,
+
+    /// Handedness of the file's coordinate system [right, left]
+    #[arg(long, default_value = "right")]
+    handedness: Handedness,
+
+    /// Scale applied to the model, e.g. 0.001 for a file in millimetres
+    #[arg(long, default_value_t = 1., value_parser = parse_scale)]
+    scale: f32,
+
+    /// Moves the center of the model to the origin
+    #[arg(long)]
+    recenter: bool,
+}
+
+impl ImportArgs {
+    fn options(&self) -> ImportOptions {
+        let options = ImportOptions::default()
+            .with_up(self.up)
+            .with_handedness(self.handedness)
+            .with_scale(self.scale)
+            .with_recenter(self.recenter);
+        match self.format {
+            Some(format) => options.with_format(format),
+            None => options,
+        }
+    }
+}
+
+/// How often the file is checked for changes with --watch
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Modification time and size of a file, which change when it is written
+fn file_stamp(path: &str) -> Option<(SystemTime, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Moves the camera around `center`, keeping its distance, so that it faces `center` again
+fn orbit(camera: &mut Camera, center: Vec3) {
+    camera.pos = center - camera.forward() * (camera.pos - center).length();
+}
+
+/// Moves the camera `amount` of its distance towards `center`,
+/// or shrinks the view of an orthographic camera by `amount`
+fn zoom(camera: &mut Camera, center: Vec3, amount: f32) {
+    match &mut camera.projection {
+        Projection::Perspective { .. } => {
+            camera.pos += camera.forward() * (camera.pos - center).length() * amount;
+        }
+        Projection::Orthographic { height } => *height *= 1. - amount,
+    }
+}
+
+/// Switches between a perspective and an orthographic projection,
+/// keeping the size of things at `center` on the screen
+fn toggle_projection(camera: &mut Camera, center: Vec3, fov: f32) {
+    let tan = |fov: f32| (fov.to_radians() / 2.).tan();
+    camera.projection = match camera.projection {
+        Projection::Perspective { fov } => Projection::Orthographic {
+            height: 2. * (camera.pos - center).length() * tan(fov),
+        },
+        Projection::Orthographic { height } => {
+            camera.pos = center - camera.forward() * height / 2. / tan(fov);
+            Projection::Perspective { fov }
+        }
+    };
+}
+
+fn parse_fov(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(fov) if fov > 0. && fov < 180. => Ok(fov),
+        Ok(_) => Err("field of view must be between 0 and 180 degrees".to_string()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+fn parse_part_color(s: &str) -> Result<(String, Vec3), String> {
+    let (name, color) = s
+        .rsplit_once('=')
+        .ok_or("expected a part and a color like wheel=ff8000")?;
+    let color = color.strip_prefix('#').unwrap_or(color);
+    let rgb = match u32::from_str_radix(color, 16) {
+        Ok(rgb) if color.len() == 6 => rgb,
+        _ => return Err(format!("expected a hex color like ff8000, got `{color}`")),
+    };
+    let channel = |shift: u32| ((rgb >> shift) & 0xff) as f32;
+    Ok((
+        name.to_string(),
+        Vec3::new(channel(16), channel(8), channel(0)),
+    ))
+}
+
+fn parse_cell_aspect(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(aspect) if aspect.is_normal() && aspect > 0. => Ok(aspect),
+        Ok(_) => Err("cell aspect must be positive and finite".to_string()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+fn parse_size(s: &str) -> Result<(usize, usize), String> {
+    let (w, h) = s
+        .split_once(['x', 'X'])
+        .ok_or("expected a size like 800x600")?;
+    match (w.parse::<usize>(), h.parse::<usize>()) {
+        (Ok(w), Ok(h)) if w > 0 && h > 0 => Ok((w, h)),
+        (Ok(_), Ok(_)) => Err("width and height must be positive".to_string()),
+        (Err(err), _) | (_, Err(err)) => Err(err.to_string()),
+    }
+}
+
+fn parse_scale(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(scale) if scale.is_normal() => Ok(scale),
+        Ok(_) => Err("scale must be non-zero and finite".to_string()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// Loads a model from a file, or from stdin if `path` is `-`
+fn load_or_exit(path: &str, options: &ImportOptions) -> Model {
+    let model = if path == "-" {
+        if options.format.is_none() {
+            eprintln!("error: --format is required to read from stdin");
+            std::process::exit(1);
+        }
+        terminal_renderer::loader::load_from_reader(io::stdin().lock(), options)
+            .map_err(|err| err.in_file("<stdin>"))
+    } else {
+        load_with_progress_bar(path, options)
+    };
+    match model {
+        Ok(model) => model,
+        Err(err) => {
+            eprintln!("error: {err}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Loads a model from a file, showing a progress bar on stderr if it is a terminal
+fn load_with_progress_bar(path: &str, options: &ImportOptions) -> Result<Model, MeshError> {
+    let show_progress = io::stderr().is_terminal();
+    let progress = |done: usize, total: usize| {
+        if show_progress {
+            let width = 30;
+            let filled = done * width / total.max(1);
+            eprint!(
+                "\rloading [{}{}] {}%",
+                "#".repeat(filled),
+                "-".repeat(width - filled),
+                done * 100 / total.max(1)
+            );
+        }
+    };
+    let model = terminal_renderer::loader::load_with_progress(path, options, progress);
+    if show_progress {
+        crossterm::execute!(
+            io::stderr(),
+            cursor::MoveToColumn(0),
+            terminal::Clear(terminal::ClearType::CurrentLine)
+        )
+        .unwrap();
+    }
+    model
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let args = Args::parse();
-    let m = terminal_renderer::loader::load_obj(args.path);
-    let mut mesh = terminal_renderer::math::Mesh::new(vec![]);
-    if let Ok(x) = m {
-        mesh = x;
-    } else {
-        match m {
-            Err(terminal_renderer::loader::MeshError::InvalidMeshError) => panic!("Invalid mesh"),
-            Err(terminal_renderer::loader::MeshError::FileNotFoundError) => {
-                panic!("File Not Found")
-            }
-            Err(terminal_renderer::loader::MeshError::UTF8Error) => panic!("Invalid UTF8"),
-            _ => (),
+    if let Some(Command::Convert {
+        input,
+        output,
+        import,
+    }) = &args.command
+    {
+        let mesh = load_or_exit(input, &import.options()).mesh();
+        if let Err(err) = terminal_renderer::exporter::save(&mesh, output) {
+            eprintln!("error: {output}: {err}");
+            std::process::exit(1);
         }
+        return Ok(());
     }
+    let Some(path) = &args.path else {
+        unreachable!("--path is required without a subcommand")
+    };
+    if args.watch && path == "-" {
+        eprintln!("error: --watch needs a file, not stdin");
+        std::process::exit(1);
+    }
+    let options = args.import.options();
+    let mut model = load_or_exit(path, &options);
 
-    if args.count_tris {
-        println!("{}", mesh.tris.len());
+    if args.list_parts {
+        for part in &model.parts {
+            let name = if part.name.is_empty() {
+                "(unnamed)"
+            } else {
+                &part.name
+            };
+            let material = part.material.as_deref().unwrap_or("-");
+            println!("{name}\t{} tris\t{material}", part.mesh.tri_count());
+        }
         return Ok(());
     }
-    let mut screen = terminal_renderer::renderer::Screen::new(1.5);
-    let (mut max_x, mut min_x, mut max_y, mut min_y, mut max_z, mut min_z) =
-        (f32::MIN, f32::MAX, f32::MIN, f32::MAX, f32::MIN, f32::MAX);
 
-    let count = (mesh.tris.len() * 3) as f32;
-    for tri in mesh.tris.iter() {
-        for v in [tri.v0, tri.v1, tri.v2] {
-            max_x = max_x.max(v.x);
-            min_x = min_x.min(v.x);
-            max_y = max_y.max(v.y);
-            min_y = min_y.min(v.y);
-            max_z = max_z.max(v.z);
-            min_z = min_z.min(v.z);
+    let colored_parts = args.color.iter().map(|(name, _)| name);
+    for name in args.hide.iter().chain(&args.only).chain(colored_parts) {
+        if model.parts_named(name).next().is_none() {
+            eprintln!("error: no part named `{name}`, see --list-parts");
+            std::process::exit(1);
         }
     }
+    let shown_mesh = |model: &mut Model| {
+        for (name, color) in &args.color {
+            model.set_part_color(name, *color);
+        }
+        model.mesh_filtered(|part| {
+            !args.hide.contains(&part.name)
+                && (args.only.is_empty() || args.only.contains(&part.name))
+        })
+    };
+    let mut mesh = shown_mesh(&mut model);
 
-    let mut sum_point = Vec3::new(
-        (max_x + min_x) / 2.,
-        (max_y + min_y) / 2.,
-        (max_z + min_z) / 2.,
-    );
+    if args.count_tris {
+        println!("{}", mesh.tri_count());
+        return Ok(());
+    }
 
-    let largest = mesh.tris.iter().fold(0.0f32, |acc, tri| {
-        acc.max((tri.v0 - sum_point).length())
-            .max((tri.v1 - sum_point).length())
-            .max((tri.v2 - sum_point).length())
-    });
+    // Without --chars the frame is drawn with truecolor half blocks
+    let chars = match args.chars {
+        Some(chars) if chars.is_empty() => renderer::DEFAULT_CHARS.to_vec(),
+        Some(chars) => chars,
+        None => vec![],
+    };
+    let (min, max) = mesh.bounds().unwrap_or((Vec3::ZERO, Vec3::ZERO));
+    let mut sum_point = (min + max) / 2.;
 
-    let mut camera = terminal_renderer::renderer::Camera::new(
-        sum_point + Vec3::new(0., 0., largest),
-        Vec3::new(0., PI, 0.),
-    );
+    let largest = mesh
+        .positions
+        .iter()
+        .fold(0.0f32, |acc, v| acc.max((*v - sum_point).length()));
+
+    let mut camera = Camera::new(sum_point, Quat::IDENTITY)
+        .with_projection(Projection::Perspective { fov: args.fov });
+    camera.set_view(args.view, sum_point, largest);
+    if args.ortho {
+        toggle_projection(&mut camera, sum_point, args.fov);
+    }
+
+    if let Some(output) = &args.output {
+        let extension = Path::new(output)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        let text = output == "-" || matches!(extension.as_deref(), Some("ans" | "txt"));
+        let (w, h, pixel_aspect) = if text {
+            // Every terminal cell holds two pixels on top of each other
+            let (cols, rows) = args.size.unwrap_or((80, 24));
+            (cols, rows.saturating_mul(2), args.cell_aspect / 2.)
+        } else {
+            let (w, h) = args.size.unwrap_or((800, 600));
+            (w, h, 1.)
+        };
+        // Fail before spending time on rendering
+        let checked = if text {
+            exporter::check_size(w, h)
+        } else {
+            exporter::check_image(output, w, h)
+        };
+        if let Err(err) = checked {
+            eprintln!("error: {output}: {err}");
+            std::process::exit(1);
+        }
+        let mut renderer = Renderer::new(pixel_aspect);
+        let mut frame = Framebuffer::new(w, h);
+        if args.octree {
+            renderer.render_octree(&camera, &mesh, &mut frame, 500f32);
+        } else {
+            renderer.render(&camera, &mesh, &mut frame);
+        }
+        let result = if !text {
+            exporter::save_image(&frame, output)
+        } else if output == "-" {
+            io::stdout().write_all(renderer::ansi_text(&frame, &chars).as_bytes())
+        } else {
+            std::fs::write(output, renderer::ansi_text(&frame, &chars))
+        };
+        if let Err(err) = result {
+            eprintln!("error: {output}: {err}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let mut screen = renderer::Screen::new().with_color_threshold(args.color_threshold);
+    // Every terminal cell holds two pixels on top of each other
+    let mut renderer = Renderer::new(args.cell_aspect / 2.);
+    let mut frame = Framebuffer::default();
+    let recorder = args.record.as_deref().map(|record| {
+        Recorder::create(record, screen.w, screen.h / 2, renderer.pixel_aspect)
+            .map_err(|err| format!("{record}: {err}"))
+    });
+    let mut recorder = match recorder.transpose() {
+        Ok(recorder) => recorder,
+        Err(err) => {
+            drop(screen);
+            eprintln!("error: {err}");
+            std::process::exit(1);
+        }
+    };
 
     crossterm::execute!(io::stdout(), event::EnableMouseCapture).unwrap();
     let mut last_mouse_pos = Vec2::new(0., 0.);
+    let mut stamp = args.watch.then(|| file_stamp(path)).flatten();
+    // Error from the last reload, shown until the file loads again
+    let mut reload_error = None;
+    // Error that stopped the recording
+    let mut record_error = None;
+    let mut redraw = true;
 
     loop {
-        screen.update_size();
-        if args.octree {
-            screen.render_octree(&camera, &mesh, &args.chars, 500f32);
-        } else {
-            screen.render(&camera, &mesh, &args.chars);
+        if redraw {
+            screen.update_size();
+            // Only the rows that are shown are rendered and recorded
+            let (w, h) = screen.frame_size();
+            frame.resize(w, h);
+            if args.octree {
+                renderer.render_octree(&camera, &mesh, &mut frame, 500f32);
+            } else {
+                renderer.render(&camera, &mesh, &mut frame);
+            }
+            let text = screen.frame_text(&frame, &chars);
+            print!("{text}");
+            let _ = io::stdout().flush();
+            let recorded = recorder
+                .as_mut()
+                .map(|recorder| recorder.record(&frame, &text, screen.w, screen.h / 2));
+            if let Some(Err(err)) = recorded {
+                // Keep what was recorded so far and carry on without recording
+                if let Some(recorder) = recorder.take() {
+                    let _ = recorder.finish();
+                }
+                record_error = Some(format!("recording stopped: {err}"));
+            }
+            if args.watch || args.record.is_some() {
+                let status = reload_error.as_ref().or(record_error.as_ref());
+                screen.status_line(status.map_or("", String::as_str));
+            }
+            while let Ok(true) = event::poll(Duration::from_millis(0)) {
+                let _ = event::read();
+            }
         }
-        while let Ok(true) = event::poll(Duration::from_millis(0)) {
-            let _ = event::read();
+        redraw = true;
+
+        if args.watch && !event::poll(WATCH_INTERVAL)? {
+            let new_stamp = file_stamp(path);
+            if new_stamp == stamp {
+                redraw = false;
+            } else {
+                stamp = new_stamp;
+                match terminal_renderer::loader::load_with_options(path, &options) {
+                    Ok(mut model) => {
+                        mesh = shown_mesh(&mut model);
+                        reload_error = None;
+                    }
+                    Err(err) => reload_error = Some(format!("reload failed: {err}")),
+                }
+            }
+            continue;
         }
 
         match event::read()? {
-            Event::Key(e) => match e {
-                KeyEvent {
-                    code,
-                    modifiers: _,
-                    kind: _,
-                    state: _,
-                } => match code {
-                    KeyCode::Left => {
-                        camera.rotation = camera.rotation.add(Vec3::new(0., -0.1, 0.));
-                        camera.pos = {
-                            let l = -(camera.pos - sum_point).length();
-                            let dir = Vec3::new(0., 0., 1.).rotate(camera.rotation);
-                            sum_point + dir * l
-                        }
-                    }
-                    KeyCode::Right => {
-                        camera.rotation = camera.rotation.add(Vec3::new(0., 0.1, 0.));
-                        camera.pos = {
-                            let l = -(camera.pos - sum_point).length();
-                            let dir = Vec3::new(0., 0., 1.).rotate(camera.rotation);
-                            sum_point + dir * l
-                        }
-                    }
-                    KeyCode::Up => {
-                        if camera.rotation.x < PI / 2. {
-                            camera.rotation = camera.rotation.add(Vec3::new(0.1, 0., 0.));
-                        }
-                        camera.pos = {
-                            let l = -(camera.pos - sum_point).length();
-                            let dir = Vec3::new(0., 0., 1.).rotate(camera.rotation);
-                            sum_point + dir * l
-                        }
-                    }
-                    KeyCode::Down => {
-                        if camera.rotation.x > -PI / 2. {
-                            camera.rotation = camera.rotation.add(Vec3::new(-0.1, 0., 0.));
-                        }
-                        camera.pos = {
-                            let l = -(camera.pos - sum_point).length();
-                            let dir = Vec3::new(0., 0., 1.).rotate(camera.rotation);
-                            sum_point + dir * l
-                        }
-                    }
-                    KeyCode::Char('w') => {
-                        camera.pos = camera.pos.add(
-                            Vec3::new(0., 0., (camera.pos - sum_point).length() * 0.1)
-                                .rotate(camera.rotation),
-                        );
-                    }
-                    KeyCode::Char('s') => {
-                        camera.pos = camera.pos.add(
-                            Vec3::new(0., 0., -(camera.pos - sum_point).length() * 0.1)
-                                .rotate(camera.rotation),
-                        );
-                    }
-                    KeyCode::Char('e') => {
-                        crossterm::terminal::disable_raw_mode();
-                        crossterm::execute!(io::stdout(), event::DisableMouseCapture).unwrap();
-                        panic!("exit");
-                        println!("\x1b[?25h");
-                    }
-                    KeyCode::Char('b') => camera.rotation.z += 0.1,
-                    _ => (),
-                },
+            Event::Key(KeyEvent { code, .. }) => match code {
+                KeyCode::Left => {
+                    camera.yaw(-0.1);
+                    orbit(&mut camera, sum_point);
+                }
+                KeyCode::Right => {
+                    camera.yaw(0.1);
+                    orbit(&mut camera, sum_point);
+                }
+                KeyCode::Up => {
+                    camera.pitch(0.1);
+                    orbit(&mut camera, sum_point);
+                }
+                KeyCode::Down => {
+                    camera.pitch(-0.1);
+                    orbit(&mut camera, sum_point);
+                }
+                KeyCode::Char('w') => zoom(&mut camera, sum_point, 0.1),
+                KeyCode::Char('s') => zoom(&mut camera, sum_point, -0.1),
+                KeyCode::Char('p') => toggle_projection(&mut camera, sum_point, args.fov),
+                KeyCode::Char(c @ '1'..='4') => {
+                    let view =
+                        [View::Front, View::Top, View::Side, View::Iso][c as usize - '1' as usize];
+                    camera.set_view(view, sum_point, (camera.pos - sum_point).length());
+                }
+                KeyCode::Char('e') => {
+                    crossterm::execute!(io::stdout(), event::DisableMouseCapture).unwrap();
+                    break;
+                }
+                KeyCode::Char('b') => camera.roll(0.1),
+                _ => (),
             },
 
             Event::Mouse(e) => {
@@ -186,40 +544,30 @@ fn main() -> Result<(), Box<dyn Error>> {
 
                         if e.modifiers == event::KeyModifiers::CONTROL {
                             let l = -(camera.pos - sum_point).length();
-                            sum_point +=
-                                Vec3::new(dx * l / screen.w as f32, -dy * l / screen.h as f32, 0.)
-                                    .rotate(camera.rotation);
-                            camera.pos = {
-                                let dir = Vec3::new(0., 0., 1.).rotate(camera.rotation);
-                                sum_point + dir * l
-                            }
+                            sum_point += camera.orientation
+                                * Vec3::new(
+                                    dx * l / screen.w as f32,
+                                    -dy * l / screen.h as f32,
+                                    0.,
+                                );
+                            orbit(&mut camera, sum_point);
                         } else {
-                            camera.rotation +=
-                                Vec3::new(dy * 3. / screen.h as f32, dx * 3. / screen.w as f32, 0.);
-
-                            camera.pos = {
-                                let l = -(camera.pos - sum_point).length();
-                                let dir = Vec3::new(0., 0., 1.).rotate(camera.rotation);
-                                sum_point + dir * l
-                            }
+                            camera.pitch(dy * 3. / screen.h as f32);
+                            camera.yaw(dx * 3. / screen.w as f32);
+                            orbit(&mut camera, sum_point);
                         }
                     }
-                    event::MouseEventKind::ScrollDown => {
-                        camera.pos = camera.pos.add(
-                            Vec3::new(0., 0., (camera.pos - sum_point).length() * 0.1)
-                                .rotate(camera.rotation),
-                        );
-                    }
-                    event::MouseEventKind::ScrollUp => {
-                        camera.pos = camera.pos.add(
-                            Vec3::new(0., 0., -(camera.pos - sum_point).length() * 0.1)
-                                .rotate(camera.rotation),
-                        );
-                    }
+                    event::MouseEventKind::ScrollDown => zoom(&mut camera, sum_point, 0.1),
+                    event::MouseEventKind::ScrollUp => zoom(&mut camera, sum_point, -0.1),
                     _ => (),
                 }
             }
             _ => (),
         }
     }
+
+    if let Some(recorder) = recorder {
+        recorder.finish()?;
+    }
+    Ok(())
 }
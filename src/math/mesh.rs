@@ -0,0 +1,679 @@
+use std::{collections::HashMap, ops::Range, sync::Arc};
+
+use crate::glam::{Mat3, Vec2, Vec3};
+use crate::texture::Texture;
+
+use super::{hit_triangle, Ray, Tri};
+
+const WHITE: Vec3 = Vec3::new(255., 255., 255.);
+
+/// Color and texture shared by a set of triangles
+#[derive(Clone, Debug, Default)]
+pub struct Surface {
+    /// Color in the 0..255 range, multiplied with the texture and vertex colors
+    pub color: Vec3,
+    pub texture: Option<Arc<Texture>>,
+}
+
+impl Surface {
+    pub fn new(color: Vec3) -> Self {
+        Self {
+            color,
+            texture: None,
+        }
+    }
+
+    pub fn with_texture(self, texture: Arc<Texture>) -> Self {
+        Self {
+            texture: Some(texture),
+            ..self
+        }
+    }
+}
+
+/// Triangle mesh with shared vertices.
+///
+/// Every vertex attribute stream has one entry per position, `uvs` and `colors`
+/// are left empty when no vertex has them.
+#[derive(Clone, Debug, Default)]
+pub struct Mesh {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<Vec2>,
+    /// Vertex colors in the 0..255 range
+    pub colors: Vec<Vec3>,
+    /// Vertex indices of every triangle
+    pub indices: Vec<[u32; 3]>,
+    pub surfaces: Vec<Surface>,
+    /// Index into `surfaces` for every triangle
+    pub tri_surfaces: Vec<u32>,
+}
+
+impl Mesh {
+    pub fn tri_count(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Positions of the corners of a triangle
+    pub fn corners(&self, tri: usize) -> [Vec3; 3] {
+        self.indices[tri].map(|idx| self.positions[idx as usize])
+    }
+
+    /// Builds a standalone `Tri` from the triangle with index `tri`
+    pub fn tri(&self, tri: usize) -> Tri {
+        let indices = self.indices[tri].map(|idx| idx as usize);
+        let [v0, v1, v2] = indices.map(|idx| self.positions[idx]);
+        let surface = &self.surfaces[self.tri_surfaces[tri] as usize];
+
+        let mut result =
+            Tri::new(v0, v1, v2, surface.color).with_normals(indices.map(|idx| self.normals[idx]));
+        if !self.colors.is_empty() {
+            result = result
+                .with_vertex_colors(indices.map(|idx| self.colors[idx] * surface.color / 255.));
+        }
+        if let (Some(texture), false) = (&surface.texture, self.uvs.is_empty()) {
+            result = result.with_texture(indices.map(|idx| self.uvs[idx]), texture.clone());
+        }
+        result
+    }
+
+    /// All triangles as standalone `Tri`s
+    pub fn tris(&self) -> impl Iterator<Item = Tri> + '_ {
+        (0..self.tri_count()).map(|tri| self.tri(tri))
+    }
+
+    /// See `hit_triangle`
+    pub fn hit(&self, tri: usize, ray: &Ray) -> Option<(f32, Vec3)> {
+        hit_triangle(self.corners(tri), ray)
+    }
+
+    /// Interpolated vertex normal at the barycentric coordinates `bary` (as returned by `Mesh::hit`)
+    pub fn normal_at(&self, tri: usize, bary: Vec3) -> Vec3 {
+        let [n0, n1, n2] = self.indices[tri].map(|idx| self.normals[idx as usize]);
+        n0 * bary.x + n1 * bary.y + n2 * bary.z
+    }
+
+    /// Color at the barycentric coordinates `bary` (as returned by `Mesh::hit`)
+    pub fn color_at(&self, tri: usize, bary: Vec3) -> Vec3 {
+        let indices = self.indices[tri].map(|idx| idx as usize);
+        let surface = &self.surfaces[self.tri_surfaces[tri] as usize];
+        let mut color = surface.color;
+        if !self.colors.is_empty() {
+            let [c0, c1, c2] = indices.map(|idx| self.colors[idx]);
+            color *= (c0 * bary.x + c1 * bary.y + c2 * bary.z) / 255.;
+        }
+        if let (Some(texture), false) = (&surface.texture, self.uvs.is_empty()) {
+            let [t0, t1, t2] = indices.map(|idx| self.uvs[idx]);
+            color *= texture.sample(t0 * bary.x + t1 * bary.y + t2 * bary.z) / 255.;
+        }
+        color
+    }
+
+    /// Smallest and largest corner of the bounding box, `None` for an empty mesh
+    pub fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        self.positions.iter().fold(None, |bounds, v| {
+            let (min, max) = bounds.unwrap_or((*v, *v));
+            Some((min.min(*v), max.max(*v)))
+        })
+    }
+
+    /// Applies `linear`, which must be invertible, followed by `translation` to every vertex
+    pub fn transform(&mut self, linear: Mat3, translation: Vec3) {
+        let normal_matrix = linear.inverse().transpose();
+        for v in &mut self.positions {
+            *v = linear * *v + translation;
+        }
+        for n in &mut self.normals {
+            *n = (normal_matrix * *n).normalize_or_zero();
+        }
+    }
+
+    /// Transformed copy of the mesh, like `transform`
+    pub fn transformed(&self, linear: Mat3, translation: Vec3) -> Self {
+        let normal_matrix = linear.inverse().transpose();
+        Self {
+            positions: self
+                .positions
+                .iter()
+                .map(|v| linear * *v + translation)
+                .collect(),
+            normals: self
+                .normals
+                .iter()
+                .map(|n| (normal_matrix * *n).normalize_or_zero())
+                .collect(),
+            uvs: self.uvs.clone(),
+            colors: self.colors.clone(),
+            indices: self.indices.clone(),
+            surfaces: self.surfaces.clone(),
+            tri_surfaces: self.tri_surfaces.clone(),
+        }
+    }
+
+    /// Appends the vertices, triangles and surfaces of `other`
+    pub fn append(&mut self, other: &Mesh) {
+        let vertex_offset = self.positions.len() as u32;
+        let surface_offset = self.surfaces.len() as u32;
+
+        let (len, other_len) = (self.positions.len(), other.positions.len());
+        append_stream(&mut self.uvs, &other.uvs, len, other_len, Vec2::ZERO);
+        append_stream(&mut self.colors, &other.colors, len, other_len, WHITE);
+        self.positions.extend_from_slice(&other.positions);
+        self.normals.extend_from_slice(&other.normals);
+
+        self.indices.extend(
+            other
+                .indices
+                .iter()
+                .map(|tri| tri.map(|idx| idx + vertex_offset)),
+        );
+        self.surfaces.extend_from_slice(&other.surfaces);
+        self.tri_surfaces
+            .extend(other.tri_surfaces.iter().map(|s| s + surface_offset));
+    }
+
+    /// Copies a range of triangles into a new mesh, keeping only the vertices and surfaces they use
+    pub fn extract(&self, tris: Range<usize>) -> Mesh {
+        let mut vertices = HashMap::new();
+        let mut surfaces = HashMap::new();
+        let mut result = Mesh::default();
+        for tri in tris {
+            let indices = self.indices[tri].map(|idx| {
+                *vertices.entry(idx).or_insert_with(|| {
+                    let idx = idx as usize;
+                    result.positions.push(self.positions[idx]);
+                    result.normals.push(self.normals[idx]);
+                    if let Some(uv) = self.uvs.get(idx) {
+                        result.uvs.push(*uv);
+                    }
+                    if let Some(color) = self.colors.get(idx) {
+                        result.colors.push(*color);
+                    }
+                    result.positions.len() as u32 - 1
+                })
+            });
+            let surface = *surfaces.entry(self.tri_surfaces[tri]).or_insert_with(|| {
+                result
+                    .surfaces
+                    .push(self.surfaces[self.tri_surfaces[tri] as usize].clone());
+                result.surfaces.len() as u32 - 1
+            });
+            result.indices.push(indices);
+            result.tri_surfaces.push(surface);
+        }
+        result
+    }
+
+    /// Replaces all normals with smooth normals, averaged over the faces sharing a vertex
+    /// and weighted by their area
+    pub fn generate_normals(&mut self) {
+        let mut normals = vec![Vec3::ZERO; self.positions.len()];
+        for (tri, indices) in self.indices.iter().enumerate() {
+            let [v0, v1, v2] = self.corners(tri);
+            // The cross product's length is twice the area
+            let normal = (v1 - v0).cross(v2 - v0);
+            for idx in indices {
+                normals[*idx as usize] += normal;
+            }
+        }
+        self.normals = normals.iter().map(|n| n.normalize_or_zero()).collect();
+    }
+
+    /// Merges vertices with identical attributes
+    pub fn weld(&mut self) {
+        let mut vertices = HashMap::new();
+        let mut remap = Vec::with_capacity(self.positions.len());
+        let mut welded = Mesh::default();
+        for idx in 0..self.positions.len() {
+            let uv = self.uvs.get(idx).copied();
+            let color = self.colors.get(idx).copied();
+            let key = (
+                key_bits(self.positions[idx].to_array()),
+                key_bits(self.normals[idx].to_array()),
+                uv.map(|uv| key_bits(uv.to_array())),
+                color.map(|color| key_bits(color.to_array())),
+            );
+            remap.push(*vertices.entry(key).or_insert_with(|| {
+                welded.positions.push(self.positions[idx]);
+                welded.normals.push(self.normals[idx]);
+                welded.uvs.extend(uv);
+                welded.colors.extend(color);
+                welded.positions.len() as u32 - 1
+            }));
+        }
+        self.positions = welded.positions;
+        self.normals = welded.normals;
+        self.uvs = welded.uvs;
+        self.colors = welded.colors;
+        for tri in &mut self.indices {
+            *tri = tri.map(|idx| remap[idx as usize]);
+        }
+    }
+}
+
+/// Bits of coordinates to compare them in hash maps, with -0.0 equal to 0.0
+fn key_bits<const N: usize>(coords: [f32; N]) -> [u32; N] {
+    coords.map(|c| if c == 0. { 0 } else { c.to_bits() })
+}
+
+/// Appends an optional vertex stream, padding it with `default` when only one of the meshes has it
+fn append_stream<T: Copy>(
+    stream: &mut Vec<T>,
+    other: &[T],
+    len: usize,
+    other_len: usize,
+    default: T,
+) {
+    if stream.is_empty() && other.is_empty() {
+        return;
+    }
+    stream.resize(len, default);
+    match other.is_empty() {
+        true => stream.resize(len + other_len, default),
+        false => stream.extend_from_slice(other),
+    }
+}
+
+/// Collects `Tri`s into a mesh without shared vertices
+impl FromIterator<Tri> for Mesh {
+    fn from_iter<I: IntoIterator<Item = Tri>>(tris: I) -> Self {
+        let mut builder = MeshBuilder::new();
+        for tri in tris {
+            // Vertex colors replace the color of the triangle
+            let mut surface = match tri.vertex_colors {
+                Some(_) => Surface::new(WHITE),
+                None => Surface::new(tri.color),
+            };
+            if let Some(texture) = &tri.texture {
+                surface = surface.with_texture(texture.clone());
+            }
+            let surface = builder.add_surface(surface);
+            let corners = [tri.v0, tri.v1, tri.v2];
+            let indices = [0, 1, 2].map(|i| {
+                let mut vertex = Vertex::new(corners[i]).with_normal(tri.normals[i]);
+                if tri.texture.is_some() {
+                    vertex = vertex.with_uv(tri.uvs[i]);
+                }
+                if let Some(colors) = tri.vertex_colors {
+                    vertex = vertex.with_color(colors[i]);
+                }
+                builder.add_vertex(vertex)
+            });
+            builder.add_tri(indices, surface);
+        }
+        builder.build()
+    }
+}
+
+/// A vertex passed to `MeshBuilder`, attributes left out are filled in when building
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Vertex {
+    pub position: Vec3,
+    /// Generated when missing, from the faces around all vertices without a normal
+    /// at the same position and in the same `smoothing_group`
+    pub normal: Option<Vec3>,
+    pub smoothing_group: u32,
+    pub uv: Option<Vec2>,
+    /// Color in the 0..255 range, white when missing
+    pub color: Option<Vec3>,
+}
+
+impl Vertex {
+    pub fn new(position: Vec3) -> Self {
+        Self {
+            position,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_normal(self, normal: Vec3) -> Self {
+        Self {
+            normal: Some(normal),
+            ..self
+        }
+    }
+
+    pub fn with_smoothing_group(self, smoothing_group: u32) -> Self {
+        Self {
+            smoothing_group,
+            ..self
+        }
+    }
+
+    pub fn with_uv(self, uv: Vec2) -> Self {
+        Self {
+            uv: Some(uv),
+            ..self
+        }
+    }
+
+    pub fn with_color(self, color: Vec3) -> Self {
+        Self {
+            color: Some(color),
+            ..self
+        }
+    }
+}
+
+/// Builds a `Mesh` one vertex and triangle at a time
+#[derive(Default)]
+pub struct MeshBuilder {
+    vertices: Vec<Vertex>,
+    indices: Vec<[u32; 3]>,
+    surfaces: Vec<Surface>,
+    tri_surfaces: Vec<u32>,
+}
+
+impl MeshBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len()
+    }
+
+    pub fn tri_count(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// Adds a vertex and returns its index
+    pub fn add_vertex(&mut self, vertex: Vertex) -> u32 {
+        self.vertices.push(vertex);
+        self.vertices.len() as u32 - 1
+    }
+
+    /// Adds a surface and returns its index
+    pub fn add_surface(&mut self, surface: Surface) -> u32 {
+        self.surfaces.push(surface);
+        self.surfaces.len() as u32 - 1
+    }
+
+    /// Adds a triangle between three vertices added before
+    pub fn add_tri(&mut self, indices: [u32; 3], surface: u32) {
+        self.indices.push(indices);
+        self.tri_surfaces.push(surface);
+    }
+
+    /// Builds the mesh, generating the missing normals from the faces around each vertex
+    pub fn build(self) -> Mesh {
+        let has_uvs = self.vertices.iter().any(|v| v.uv.is_some());
+        let has_colors = self.vertices.iter().any(|v| v.color.is_some());
+        let mut mesh = Mesh {
+            positions: self.vertices.iter().map(|v| v.position).collect(),
+            normals: Vec::new(),
+            uvs: match has_uvs {
+                true => self
+                    .vertices
+                    .iter()
+                    .map(|v| v.uv.unwrap_or_default())
+                    .collect(),
+                false => Vec::new(),
+            },
+            colors: match has_colors {
+                true => self
+                    .vertices
+                    .iter()
+                    .map(|v| v.color.unwrap_or(WHITE))
+                    .collect(),
+                false => Vec::new(),
+            },
+            indices: self.indices,
+            surfaces: self.surfaces,
+            tri_surfaces: self.tri_surfaces,
+        };
+
+        // Area weighted sum of the face normals around every position and smoothing group
+        let smooth_key = |v: &Vertex| (key_bits(v.position.to_array()), v.smoothing_group);
+        let mut generated = HashMap::new();
+        for (tri, indices) in mesh.indices.iter().enumerate() {
+            let corners = indices.map(|idx| &self.vertices[idx as usize]);
+            if corners.iter().all(|v| v.normal.is_some()) {
+                continue;
+            }
+            let [v0, v1, v2] = mesh.corners(tri);
+            // The cross product's length is twice the area
+            let normal = (v1 - v0).cross(v2 - v0);
+            for v in corners.iter().filter(|v| v.normal.is_none()) {
+                *generated.entry(smooth_key(v)).or_insert(Vec3::ZERO) += normal;
+            }
+        }
+        mesh.normals = self
+            .vertices
+            .iter()
+            .map(|v| match v.normal {
+                Some(normal) => normal,
+                None => generated
+                    .get(&smooth_key(v))
+                    .map_or(Vec3::ZERO, |n| n.normalize_or_zero()),
+            })
+            .collect();
+        mesh
+    }
+}
+
+/// A named piece of a model, such as an .obj object or group
+#[derive(Clone, Debug)]
+pub struct Part {
+    pub name: String,
+    /// Material in use when the part starts
+    pub material: Option<String>,
+    /// Smoothing group in use when the part starts, `None` when smoothing is off
+    pub smoothing_group: Option<u32>,
+    pub mesh: Mesh,
+}
+
+impl Part {
+    pub fn new(name: &str, mesh: Mesh) -> Self {
+        Self {
+            name: name.to_string(),
+            material: None,
+            smoothing_group: None,
+            mesh,
+        }
+    }
+
+    /// Replaces the color of every surface of the part, textures and vertex colors are still multiplied with it
+    pub fn set_color(&mut self, color: Vec3) {
+        for surface in &mut self.mesh.surfaces {
+            surface.color = color;
+        }
+    }
+}
+
+/// A mesh made of named parts
+#[derive(Clone, Debug)]
+pub struct Model {
+    pub parts: Vec<Part>,
+}
+
+impl Model {
+    pub fn new(parts: Vec<Part>) -> Self {
+        Self { parts }
+    }
+
+    /// All parts with the given name, a name can be used by several parts
+    pub fn parts_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Part> {
+        self.parts.iter().filter(move |part| part.name == name)
+    }
+
+    /// Sets the color of all parts with the given name, returns whether there were any
+    pub fn set_part_color(&mut self, name: &str, color: Vec3) -> bool {
+        let mut found = false;
+        for part in self.parts.iter_mut().filter(|part| part.name == name) {
+            part.set_color(color);
+            found = true;
+        }
+        found
+    }
+
+    /// Bounding box of all parts, `None` for an empty model
+    pub fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        self.parts
+            .iter()
+            .filter_map(|part| part.mesh.bounds())
+            .reduce(|(min1, max1), (min2, max2)| (min1.min(min2), max1.max(max2)))
+    }
+
+    /// Applies `linear`, which must be invertible, followed by `translation` to every part
+    pub fn transform(&mut self, linear: Mat3, translation: Vec3) {
+        for part in &mut self.parts {
+            part.mesh.transform(linear, translation);
+        }
+    }
+
+    /// Transformed copy of the model, like `transform`
+    pub fn transformed(&self, linear: Mat3, translation: Vec3) -> Self {
+        Self::new(
+            self.parts
+                .iter()
+                .map(|part| Part {
+                    name: part.name.clone(),
+                    material: part.material.clone(),
+                    smoothing_group: part.smoothing_group,
+                    mesh: part.mesh.transformed(linear, translation),
+                })
+                .collect(),
+        )
+    }
+
+    /// Merges all parts into a single mesh
+    pub fn mesh(&self) -> Mesh {
+        self.mesh_filtered(|_| true)
+    }
+
+    /// Merges the parts matching `filter` into a single mesh
+    pub fn mesh_filtered(&self, filter: impl Fn(&Part) -> bool) -> Mesh {
+        let mut mesh = Mesh::default();
+        for part in self.parts.iter().filter(|part| filter(part)) {
+            mesh.append(&part.mesh);
+        }
+        mesh
+    }
+}
+
+/// A model with a single unnamed part
+impl From<Mesh> for Model {
+    fn from(mesh: Mesh) -> Self {
+        Self::new(vec![Part::new("", mesh)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triangle(color: Vec3) -> Mesh {
+        let mut builder = MeshBuilder::new();
+        let surface = builder.add_surface(Surface::new(color));
+        for position in [Vec3::ZERO, Vec3::X, Vec3::Y] {
+            builder.add_vertex(Vertex::new(position));
+        }
+        builder.add_tri([0, 1, 2], surface);
+        builder.build()
+    }
+
+    #[test]
+    fn transform_in_place() {
+        let mut mesh = triangle(WHITE);
+        let linear = Mat3::from_diagonal(Vec3::new(2., 1., -1.));
+        let copy = mesh.transformed(linear, Vec3::Y);
+        mesh.transform(linear, Vec3::Y);
+        assert_eq!(
+            mesh.positions,
+            [Vec3::Y, Vec3::new(2., 1., 0.), Vec3::new(0., 2., 0.)]
+        );
+        assert_eq!(mesh.normals, [Vec3::NEG_Z; 3]);
+        assert_eq!(mesh.positions, copy.positions);
+        assert_eq!(mesh.normals, copy.normals);
+    }
+
+    /// A square in the xy plane made of two triangles without shared vertices
+    fn square() -> Mesh {
+        let corners = [
+            Vec3::ZERO,
+            Vec3::X,
+            Vec3::Y,
+            Vec3::X,
+            Vec3::new(1., 1., 0.),
+            Vec3::Y,
+        ];
+        Mesh {
+            positions: corners.to_vec(),
+            normals: vec![Vec3::Z; 6],
+            indices: vec![[0, 1, 2], [3, 4, 5]],
+            surfaces: vec![Surface::new(WHITE)],
+            tri_surfaces: vec![0, 0],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn weld() {
+        let mut mesh = square();
+        mesh.positions[3] = Vec3::new(1., -0., 0.);
+        mesh.normals[3] = Vec3::new(-0., 0., 1.);
+        mesh.weld();
+        assert_eq!(mesh.vertex_count(), 4);
+        assert_eq!(mesh.indices, [[0, 1, 2], [1, 3, 2]]);
+
+        // Vertices with different attributes stay apart
+        let mut mesh = square();
+        mesh.uvs = vec![Vec2::ZERO, Vec2::X, Vec2::Y, Vec2::ZERO, Vec2::ONE, Vec2::Y];
+        mesh.weld();
+        assert_eq!(mesh.vertex_count(), 5);
+    }
+
+    #[test]
+    fn generate_normals() {
+        let mut mesh = square();
+        mesh.weld();
+        // Fold the square along its diagonal, the shared vertices get the weighted average
+        mesh.positions[3].z = 1.;
+        mesh.generate_normals();
+        assert_eq!(mesh.normals[0], Vec3::Z);
+        let folded = Vec3::new(-1., -1., 1.).normalize();
+        assert!(mesh.normals[3].abs_diff_eq(folded, 1e-6));
+        let average = Vec3::new(-1., -1., 2.).normalize();
+        assert!(mesh.normals[1].abs_diff_eq(average, 1e-6));
+    }
+
+    #[test]
+    fn smoothing_ignores_the_sign_of_zero() {
+        let mut builder = MeshBuilder::new();
+        let surface = builder.add_surface(Surface::new(WHITE));
+        for position in [
+            Vec3::ZERO,
+            Vec3::X,
+            Vec3::Y,
+            Vec3::new(-0., 0., -0.),
+            Vec3::Y,
+            Vec3::new(-1., 0., 1.),
+        ] {
+            builder.add_vertex(Vertex::new(position));
+        }
+        builder.add_tri([0, 1, 2], surface);
+        builder.add_tri([3, 4, 5], surface);
+        let mesh = builder.build();
+        assert_eq!(mesh.normals[0], mesh.normals[3]);
+    }
+
+    #[test]
+    fn part_color() {
+        let mut model = Model::new(vec![
+            Part::new("a", triangle(WHITE)),
+            Part::new("b", triangle(WHITE)),
+        ]);
+        let red = Vec3::new(255., 0., 0.);
+        assert!(model.set_part_color("b", red));
+        assert!(!model.set_part_color("c", red));
+
+        let mesh = model.mesh();
+        assert_eq!(mesh.color_at(0, Vec3::X), WHITE);
+        assert_eq!(mesh.color_at(1, Vec3::X), red);
+    }
+}
@@ -1,7 +1,11 @@
-use core::net;
-use std::{rc::Rc, sync::Arc};
+use std::sync::Arc;
 
-use crate::glam::{vec3, vec4, Vec3, Vec4};
+use crate::glam::{vec3, Vec2, Vec3};
+use crate::texture::Texture;
+
+mod mesh;
+
+pub use mesh::{Mesh, MeshBuilder, Model, Part, Surface, Vertex};
 
 const EPSILON: f32 = 0.01;
 
@@ -21,6 +25,13 @@ pub struct Tri {
     pub v0: Vec3,
     pub v1: Vec3,
     pub v2: Vec3,
+    /// Vertex normals for v0, v1 and v2
+    pub normals: [Vec3; 3],
+    /// Texture coordinates for v0, v1 and v2
+    pub uvs: [Vec2; 3],
+    pub texture: Option<Arc<Texture>>,
+    /// Colors for v0, v1 and v2, interpolated over the triangle instead of `color`
+    pub vertex_colors: Option<[Vec3; 3]>,
     pub color: Vec3,
     pub v_min: Vec3,
     pub v_max: Vec3,
@@ -83,16 +94,26 @@ impl Tri {
             v0.y.max(v1.y).max(v2.y),
             v0.z.max(v1.z).max(v2.z),
         );
+        let normal = (v1 - v0).cross(v2 - v0).normalize_or_zero();
         Self {
             v0,
             v1,
             v2,
+            normals: [normal; 3],
+            uvs: [Vec2::ZERO; 3],
+            texture: None,
+            vertex_colors: None,
             color,
             v_min,
             v_max,
         }
     }
 
+    /// Replaces the flat vertex normals given by `Tri::new`
+    pub fn with_normals(self, normals: [Vec3; 3]) -> Self {
+        Self { normals, ..self }
+    }
+
     /// Normal vector for triangle
     pub fn normal(&self) -> Vec3 {
         let e1 = self.v1 - self.v0; // edge 1
@@ -100,48 +121,82 @@ impl Tri {
         e1.cross(e2) // Normal Vector
     }
 
-    // Möller-Trumbore algo (https://www.scratchapixel.com/lessons/3d-basic-rendering/ray-tracing-rendering-a-triangle/moller-trumbore-ray-triangle-intersection.html)
-    pub fn hit(&self, ray: &Ray) -> Option<f32> {
-        let e1 = self.v1 - self.v0;
-        let e2 = self.v2 - self.v0;
-        let p = ray.dir.cross(e2);
-        let det = e1.dot(p);
-        // const EPSILON: f32 = 0.001;
-
-        // If determinant is close to zero the ray and triangle are parallel
-        if det.abs() < EPSILON {
-            return None;
+    /// Textures the triangle, the texture color is multiplied by `Tri::color`
+    pub fn with_texture(self, uvs: [Vec2; 3], texture: Arc<Texture>) -> Self {
+        Self {
+            uvs,
+            texture: Some(texture),
+            ..self
         }
+    }
 
-        let inv_det = 1. / det;
-        let t = ray.origin - self.v0;
-        let u = t.dot(p) * inv_det;
-        if u < 0. || u > 1. {
-            return None;
-        };
-
-        let q = t.cross(e1);
-        let v = ray.dir.dot(q) * inv_det;
-        if (v < 0. || u + v > 1.) {
-            return None;
+    /// Colors the vertices individually, `color` is set to their average
+    pub fn with_vertex_colors(self, colors: [Vec3; 3]) -> Self {
+        Self {
+            vertex_colors: Some(colors),
+            color: (colors[0] + colors[1] + colors[2]) / 3.,
+            ..self
         }
-        let t = e2.dot(q) * inv_det;
-        if t < 0. {
-            return None;
+    }
+
+    /// Color at the barycentric coordinates `bary` (as returned by `Tri::hit`)
+    pub fn color_at(&self, bary: Vec3) -> Vec3 {
+        match (&self.texture, self.vertex_colors) {
+            (Some(texture), colors) => {
+                let uv = self.uvs[0] * bary.x + self.uvs[1] * bary.y + self.uvs[2] * bary.z;
+                let tint = match colors {
+                    Some(colors) => colors[0] * bary.x + colors[1] * bary.y + colors[2] * bary.z,
+                    None => self.color,
+                };
+                texture.sample(uv) * tint / 255.
+            }
+            (None, Some(colors)) => colors[0] * bary.x + colors[1] * bary.y + colors[2] * bary.z,
+            (None, None) => self.color,
         }
-        Some(t)
     }
-}
 
-#[derive(Clone)]
-pub struct Mesh {
-    pub tris: Arc<[Tri]>,
+    /// Interpolated vertex normal at the barycentric coordinates `bary` (as returned by `Tri::hit`)
+    pub fn normal_at(&self, bary: Vec3) -> Vec3 {
+        self.normals[0] * bary.x + self.normals[1] * bary.y + self.normals[2] * bary.z
+    }
+
+    /// See `hit_triangle`
+    pub fn hit(&self, ray: &Ray) -> Option<(f32, Vec3)> {
+        hit_triangle([self.v0, self.v1, self.v2], ray)
+    }
 }
 
-impl Mesh {
-    pub fn new(tris: Vec<Tri>) -> Self {
-        Self { tris: tris.into() }
+// Möller-Trumbore algo (https://www.scratchapixel.com/lessons/3d-basic-rendering/ray-tracing-rendering-a-triangle/moller-trumbore-ray-triangle-intersection.html)
+/// Returns the distance along the ray and the barycentric coordinates of the hit (weights of v0, v1, v2)
+pub fn hit_triangle([v0, v1, v2]: [Vec3; 3], ray: &Ray) -> Option<(f32, Vec3)> {
+    let e1 = v1 - v0;
+    let e2 = v2 - v0;
+    let p = ray.dir.cross(e2);
+    let det = e1.dot(p);
+    // const EPSILON: f32 = 0.001;
+
+    // If determinant is close to zero the ray and triangle are parallel
+    if det.abs() < EPSILON {
+        return None;
+    }
+
+    let inv_det = 1. / det;
+    let t = ray.origin - v0;
+    let u = t.dot(p) * inv_det;
+    if !(0. ..=1.).contains(&u) {
+        return None;
+    };
+
+    let q = t.cross(e1);
+    let v = ray.dir.dot(q) * inv_det;
+    if v < 0. || u + v > 1. {
+        return None;
+    }
+    let t = e2.dot(q) * inv_det;
+    if t < 0. {
+        return None;
     }
+    Some((t, vec3(1. - u - v, u, v)))
 }
 
 #[derive(Debug)]
@@ -158,10 +213,10 @@ pub struct Octree {
 pub enum OctreeNode {
     None,
     Leaf {
-        tri: Vec<Arc<Tri>>,
+        tri: Vec<usize>,
     },
     Node {
-        tri: Vec<Arc<Tri>>,
+        tri: Vec<usize>,
         children: [Box<Octree>; 8],
     },
 }
@@ -189,7 +244,8 @@ impl Octree {
         }
     }
 
-    pub fn insert(&mut self, tri: Arc<Tri>) {
+    /// Inserts the triangle with index `tri` and the corners `corners`
+    pub fn insert(&mut self, tri: usize, corners: [Vec3; 3]) {
         self.inserted += 1;
 
         let insert = match self.node {
@@ -198,7 +254,7 @@ impl Octree {
             OctreeNode::Node {
                 tri: _,
                 children: _,
-            } => self.should_insert_tri(tri.clone()),
+            } => self.should_insert_tri(corners),
         };
 
         match self.node {
@@ -211,9 +267,9 @@ impl Octree {
                 tri: _,
                 ref mut children,
             } => {
-                for i in 0..8 {
+                for (i, child) in children.iter_mut().enumerate() {
                     if (insert >> i) & 1 == 1 {
-                        children[i].insert(tri.clone());
+                        child.insert(tri, corners);
                     }
                 }
             }
@@ -221,7 +277,7 @@ impl Octree {
                 tri: ref mut innertri,
             } => {
                 if innertri.len() < self.max_nodes {
-                    innertri.push(tri.clone());
+                    innertri.push(tri);
                     return;
                 }
                 let children = [
@@ -301,7 +357,7 @@ impl Octree {
                     tri: innertri.clone(),
                     children,
                 };
-                self.insert(tri);
+                self.insert(tri, corners);
             }
         }
     }
@@ -325,7 +381,8 @@ impl Octree {
         !(aabb_check_list[7] + EPSILON <= 0. || aabb_check_list[6] >= EPSILON + aabb_check_list[7])
     }
 
-    pub fn ray_search_tree(&self, ro: Vec3, rd: Vec3) -> Vec<Arc<Tri>> {
+    /// Indices of the triangles in the nodes the ray passes through
+    pub fn ray_search_tree(&self, ro: Vec3, rd: Vec3) -> Vec<usize> {
         let mut result = Vec::with_capacity(self.inserted);
 
         match self.node {
@@ -346,10 +403,10 @@ impl Octree {
         result
     }
 
-    fn should_insert_tri(&self, tri: Arc<Tri>) -> u8 {
+    fn should_insert_tri(&self, tri: [Vec3; 3]) -> u8 {
         let mut should_insert = 0u8;
         // for v in [tri.v0, tri.v1, tri.v2] {
-        if triangle_aabb_intersects(self.middle, self.bottom_right_back, tri.clone()) {
+        if triangle_aabb_intersects(self.middle, self.bottom_right_back, tri) {
             should_insert |= 0b00000001;
         }
         if triangle_aabb_intersects(
@@ -359,7 +416,7 @@ impl Octree {
                 self.bottom_right_back.y,
                 self.middle.z,
             ),
-            tri.clone(),
+            tri,
         ) {
             should_insert |= 0b00000010;
         }
@@ -370,14 +427,14 @@ impl Octree {
                 self.middle.y,
                 self.bottom_right_back.z,
             ),
-            tri.clone(),
+            tri,
         ) {
             should_insert |= 0b00000100;
         }
         if triangle_aabb_intersects(
             vec3(self.middle.x, self.top_left_front.y, self.top_left_front.z),
             vec3(self.bottom_right_back.x, self.middle.y, self.middle.z),
-            tri.clone(),
+            tri,
         ) {
             should_insert |= 0b00001000;
         }
@@ -388,21 +445,21 @@ impl Octree {
                 self.bottom_right_back.y,
                 self.bottom_right_back.z,
             ),
-            tri.clone(),
+            tri,
         ) {
             should_insert |= 0b00010000;
         }
         if triangle_aabb_intersects(
             vec3(self.top_left_front.x, self.middle.y, self.top_left_front.z),
             vec3(self.middle.x, self.bottom_right_back.y, self.middle.z),
-            tri.clone(),
+            tri,
         ) {
             should_insert |= 0b00100000;
         }
         if triangle_aabb_intersects(
             vec3(self.top_left_front.x, self.top_left_front.y, self.middle.z),
             vec3(self.middle.x, self.middle.y, self.bottom_right_back.z),
-            tri.clone(),
+            tri,
         ) {
             should_insert |= 0b01000000;
         }
@@ -413,7 +470,7 @@ impl Octree {
                 self.top_left_front.z,
             ),
             vec3(self.middle.x, self.middle.y, self.middle.z),
-            tri.clone(),
+            tri,
         ) {
             should_insert |= 0b10000000;
         }
@@ -425,14 +482,12 @@ impl Octree {
 ///
 /// `aabb_min` and `aabb_max` define the AABB.
 /// `tri` is the triangle to test.
-pub fn triangle_aabb_intersects(aabb_min: Vec3, aabb_max: Vec3, tri: Arc<Tri>) -> bool {
+pub fn triangle_aabb_intersects(aabb_min: Vec3, aabb_max: Vec3, tri: [Vec3; 3]) -> bool {
     let center = (aabb_min + aabb_max) * 0.5;
     let extents = (aabb_max - aabb_min) * 0.5;
 
     // Move triangle into AABB's local space
-    let v0 = tri.v0 - center;
-    let v1 = tri.v1 - center;
-    let v2 = tri.v2 - center;
+    let [v0, v1, v2] = tri.map(|v| v - center);
 
     let f0 = v1 - v0;
     let f1 = v2 - v1;
@@ -0,0 +1,100 @@
+use std::{
+    io::{self, Write},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Writes terminal output as an asciinema asciicast v2 file
+pub struct AsciicastWriter<W: Write> {
+    out: W,
+    size: (usize, usize),
+}
+
+impl<W: Write> AsciicastWriter<W> {
+    /// Writes the header for a terminal of `cols` by `rows` cells
+    pub fn new(mut out: W, cols: usize, rows: usize) -> io::Result<Self> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let term = std::env::var("TERM").unwrap_or_else(|_| "xterm-256color".to_string());
+        writeln!(
+            out,
+            r#"{{"version": 2, "width": {cols}, "height": {rows}, "timestamp": {timestamp}, "env": {{"TERM": {}}}}}"#,
+            json_string(&term)
+        )?;
+        Ok(Self {
+            out,
+            size: (cols, rows),
+        })
+    }
+
+    /// Writes text printed `time` after the start of the recording
+    pub fn output(&mut self, time: Duration, text: &str) -> io::Result<()> {
+        self.event(time, "o", text)
+    }
+
+    /// Writes a resize event if the terminal changed size
+    pub fn resize(&mut self, time: Duration, cols: usize, rows: usize) -> io::Result<()> {
+        if (cols, rows) == self.size {
+            return Ok(());
+        }
+        self.size = (cols, rows);
+        self.event(time, "r", &format!("{cols}x{rows}"))
+    }
+
+    fn event(&mut self, time: Duration, code: &str, data: &str) -> io::Result<()> {
+        writeln!(
+            self.out,
+            r#"[{:.6}, "{code}", {}]"#,
+            time.as_secs_f64(),
+            json_string(data)
+        )
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+fn json_string(s: &str) -> String {
+    serde_json::Value::from(s).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(out: &[u8]) -> Vec<serde_json::Value> {
+        std::str::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn header_and_events() {
+        let mut writer = AsciicastWriter::new(Vec::new(), 80, 23).unwrap();
+        writer
+            .output(Duration::from_millis(1500), "\x1b[1;1H\"\u{2584}\"\n")
+            .unwrap();
+        // Only a change of size is recorded
+        writer.resize(Duration::from_secs(2), 80, 23).unwrap();
+        writer.resize(Duration::from_secs(2), 100, 30).unwrap();
+        let out = writer.finish().unwrap();
+
+        let lines = lines(&out);
+        assert_eq!(lines.len(), 3);
+        let header = &lines[0];
+        assert_eq!(header["version"], 2);
+        assert_eq!(header["width"], 80);
+        assert_eq!(header["height"], 23);
+        assert!(header["timestamp"].is_u64());
+        assert!(header["env"]["TERM"].is_string());
+        assert_eq!(
+            lines[1],
+            serde_json::json!([1.5, "o", "\x1b[1;1H\"\u{2584}\"\n"])
+        );
+        assert_eq!(lines[2], serde_json::json!([2.0, "r", "100x30"]));
+    }
+}
@@ -0,0 +1,113 @@
+use std::{
+    io::{self, Write},
+    time::Duration,
+};
+
+use crate::{exporter::color_bytes, renderer::Framebuffer};
+
+/// Width of the block every framebuffer pixel is drawn as, its height is scaled by the pixel aspect
+const PIXEL_SIZE: usize = 4;
+/// How long the last frame is shown before the animation loops
+const LAST_FRAME_DELAY: Duration = Duration::from_secs(1);
+
+/// Writes framebuffers as the frames of a looping animated GIF
+pub struct GifWriter<W: Write> {
+    out: Option<W>,
+    encoder: Option<gif::Encoder<W>>,
+    /// Height of a framebuffer pixel divided by its width
+    pixel_aspect: f32,
+    size: (usize, usize),
+    /// The last frame and when it was shown, its delay is known once the next frame arrives
+    pending: Option<(gif::Frame<'static>, Duration)>,
+}
+
+impl<W: Write> GifWriter<W> {
+    pub fn new(out: W, pixel_aspect: f32) -> Self {
+        Self {
+            out: Some(out),
+            encoder: None,
+            pixel_aspect,
+            size: (0, 0),
+            pending: None,
+        }
+    }
+
+    /// Adds a frame shown `time` after the start of the recording.
+    /// The first frame sets the size of the GIF, later frames are cut off or padded with black to fit it.
+    pub fn frame(&mut self, time: Duration, frame: &Framebuffer) -> io::Result<()> {
+        if let Some(out) = self.out.take() {
+            let w = (frame.w * PIXEL_SIZE).max(1);
+            let h = ((frame.h * PIXEL_SIZE) as f32 * self.pixel_aspect)
+                .round()
+                .max(1.) as usize;
+            let too_large =
+                || io::Error::new(io::ErrorKind::InvalidInput, "frame too large for .gif");
+            let mut encoder = gif::Encoder::new(
+                out,
+                u16::try_from(w).map_err(|_| too_large())?,
+                u16::try_from(h).map_err(|_| too_large())?,
+                &[],
+            )
+            .map_err(gif_error)?;
+            encoder
+                .set_repeat(gif::Repeat::Infinite)
+                .map_err(gif_error)?;
+            self.encoder = Some(encoder);
+            self.size = (w, h);
+        }
+
+        let (w, h) = self.size;
+        let image = gif::Frame::from_rgb_speed(w as u16, h as u16, &self.rasterize(frame), 10);
+        if let Some((last, shown)) = self.pending.replace((image, time)) {
+            self.write(last, time.saturating_sub(shown))?;
+        }
+        Ok(())
+    }
+
+    /// Nearest neighbour scaling of the framebuffer to the size of the GIF
+    fn rasterize(&self, frame: &Framebuffer) -> Vec<u8> {
+        let (w, h) = self.size;
+        let row_height = PIXEL_SIZE as f32 * self.pixel_aspect;
+        let mut rgb = Vec::with_capacity(w * h * 3);
+        for y in 0..h {
+            let row = (y as f32 / row_height) as usize;
+            for x in 0..w {
+                let col = x / PIXEL_SIZE;
+                if col < frame.w && row < frame.h {
+                    rgb.extend(color_bytes(frame.color_at(col, row)));
+                } else {
+                    rgb.extend([0; 3]);
+                }
+            }
+        }
+        rgb
+    }
+
+    fn write(&mut self, mut image: gif::Frame<'static>, delay: Duration) -> io::Result<()> {
+        // GIF delays are in hundredths of a second
+        image.delay = (delay.as_millis() / 10).min(u16::MAX as u128) as u16;
+        match &mut self.encoder {
+            Some(encoder) => encoder.write_frame(&image).map_err(gif_error),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes the last frame and the trailer, a recording without frames writes nothing
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some((last, _)) = self.pending.take() {
+            self.write(last, LAST_FRAME_DELAY)?;
+        }
+        match (self.encoder.take(), self.out.take()) {
+            (Some(encoder), _) => encoder.into_inner(),
+            (None, Some(out)) => Ok(out),
+            (None, None) => unreachable!("the writer is either waiting for a frame or encoding"),
+        }
+    }
+}
+
+fn gif_error(err: gif::EncodingError) -> io::Error {
+    match err {
+        gif::EncodingError::Io(err) => err,
+        err => io::Error::new(io::ErrorKind::InvalidInput, err),
+    }
+}
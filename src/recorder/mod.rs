@@ -0,0 +1,82 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::Instant,
+};
+
+use crate::renderer::Framebuffer;
+
+mod asciicast;
+mod gif;
+
+pub use asciicast::AsciicastWriter;
+pub use gif::GifWriter;
+
+/// Records the frames shown in the terminal into a file, timestamped from when it was created
+pub struct Recorder {
+    start: Instant,
+    output: Output,
+}
+
+enum Output {
+    Asciicast(AsciicastWriter<BufWriter<File>>),
+    Gif(GifWriter<BufWriter<File>>),
+}
+
+impl Recorder {
+    /// Creates an asciicast v2 (.cast) or animated .gif recording, chosen by the file extension.
+    /// `cols` and `rows` are the size of the terminal and `pixel_aspect` is the height of a framebuffer pixel divided by its width.
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        cols: usize,
+        rows: usize,
+        pixel_aspect: f32,
+    ) -> io::Result<Self> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        let out = || File::create(path).map(BufWriter::new);
+        let output = match extension.as_deref() {
+            Some("cast") => Output::Asciicast(AsciicastWriter::new(out()?, cols, rows)?),
+            Some("gif") => Output::Gif(GifWriter::new(out()?, pixel_aspect)),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "unsupported recording format, expected .cast or .gif",
+                ))
+            }
+        };
+        Ok(Self {
+            start: Instant::now(),
+            output,
+        })
+    }
+
+    /// Records a frame along with the `text` printed to show it in a terminal of `cols` by `rows` cells
+    pub fn record(
+        &mut self,
+        frame: &Framebuffer,
+        text: &str,
+        cols: usize,
+        rows: usize,
+    ) -> io::Result<()> {
+        let time = self.start.elapsed();
+        match &mut self.output {
+            Output::Asciicast(writer) => {
+                writer.resize(time, cols, rows)?;
+                writer.output(time, text)
+            }
+            Output::Gif(writer) => writer.frame(time, frame),
+        }
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        match self.output {
+            Output::Asciicast(writer) => writer.finish()?.flush(),
+            Output::Gif(writer) => writer.finish()?.flush(),
+        }
+    }
+}
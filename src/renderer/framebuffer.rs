@@ -0,0 +1,43 @@
+use glam::Vec3;
+
+/// Image the renderer draws into, stored row by row from the top left.
+/// Every pixel has a color in the 0..255 range and the distance to what it hit, which is infinite if it hit nothing.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Framebuffer {
+    pub w: usize,
+    pub h: usize,
+    pub color: Vec<Vec3>,
+    pub depth: Vec<f32>,
+}
+
+impl Framebuffer {
+    pub fn new(w: usize, h: usize) -> Self {
+        Self {
+            w,
+            h,
+            color: vec![Vec3::ZERO; w * h],
+            depth: vec![f32::INFINITY; w * h],
+        }
+    }
+
+    /// Changes the size, the content is cleared if the size changed
+    pub fn resize(&mut self, w: usize, h: usize) {
+        if (w, h) != (self.w, self.h) {
+            *self = Self::new(w, h);
+        }
+    }
+
+    /// Sets every pixel to black with an infinite depth
+    pub fn clear(&mut self) {
+        self.color.fill(Vec3::ZERO);
+        self.depth.fill(f32::INFINITY);
+    }
+
+    pub fn color_at(&self, col: usize, row: usize) -> Vec3 {
+        self.color[row * self.w + col]
+    }
+
+    pub fn depth_at(&self, col: usize, row: usize) -> f32 {
+        self.depth[row * self.w + col]
+    }
+}
@@ -1,108 +1,231 @@
-use std::sync::Arc;
+use std::str::FromStr;
 
+use crate::loader::ParseOptionError;
 use crate::math::{self, Octree};
-use crossterm;
 use glam::vec3;
-use math::Rotation;
 use rayon::iter::ParallelIterator;
-use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator};
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, IntoParallelRefMutIterator};
 
-use crate::glam::Vec3;
+use crate::glam::{Mat3, Quat, Vec3};
+
+mod framebuffer;
+mod terminal;
+
+pub use framebuffer::Framebuffer;
+pub use terminal::{
+    ansi_text, FrameDiff, Screen, DEFAULT_CELL_ASPECT, DEFAULT_CHARS, DEFAULT_COLOR_THRESHOLD,
+};
+
+/// Up in the renderer's space, where y points down
+pub const UP: Vec3 = Vec3::NEG_Y;
+
+/// Range of the shading factor, surfaces facing away from the camera are still dimly lit
+/// and the brightest surfaces show their color times `MAX_SHADE`
+const MIN_SHADE: f32 = 0.2;
+const MAX_SHADE: f32 = 0.8;
+
+/// Field of view used when none is given, in degrees
+pub const DEFAULT_FOV: f32 = 70.;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    /// Field of view across the shorter side of the screen, in degrees
+    Perspective { fov: f32 },
+    /// Parallel rays covering `height` world units from the top to the bottom of the screen
+    Orthographic { height: f32 },
+}
+
+impl Default for Projection {
+    fn default() -> Self {
+        Projection::Perspective { fov: DEFAULT_FOV }
+    }
+}
+
+/// Preset directions to look at a model from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum View {
+    Front,
+    Top,
+    /// From the right
+    Side,
+    /// From the front, right and top at equal angles
+    Iso,
+}
+
+impl FromStr for View {
+    type Err = ParseOptionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "front" => Ok(View::Front),
+            "top" => Ok(View::Top),
+            "side" => Ok(View::Side),
+            "iso" => Ok(View::Iso),
+            _ => Err(ParseOptionError("expected front, top, side or iso")),
+        }
+    }
+}
+
+impl View {
+    /// Direction from the model to the camera, and the direction that is up on the screen
+    fn directions(self) -> (Vec3, Vec3) {
+        match self {
+            View::Front => (Vec3::Z, UP),
+            View::Top => (UP, Vec3::NEG_Z),
+            View::Side => (Vec3::X, UP),
+            View::Iso => ((Vec3::X + UP + Vec3::Z).normalize(), UP),
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct Camera {
     pub pos: Vec3,
-    pub rotation: Vec3,
+    /// Rotation from camera space (x right, y down, z forward) into world space
+    pub orientation: Quat,
+    pub projection: Projection,
 }
 
 impl Camera {
-    pub fn new(pos: Vec3, rotation: Vec3) -> Self {
-        Self { pos, rotation }
+    pub fn new(pos: Vec3, orientation: Quat) -> Self {
+        Self {
+            pos,
+            orientation,
+            projection: Projection::default(),
+        }
     }
-}
 
-// #[derive(Clone)]
-pub struct Screen {
-    pub w: usize,
-    pub h: usize,
-    pub focus_dist: f32,
-    octree: Octree,
-}
+    pub fn with_projection(self, projection: Projection) -> Self {
+        Self { projection, ..self }
+    }
 
-impl Drop for Screen {
-    fn drop(&mut self) {
-        crossterm::terminal::disable_raw_mode();
-        println!("\x1b[?25h");
+    /// Camera rotated by Euler angles like `Rotation::rotate`, around z, then x, then y
+    pub fn from_euler(pos: Vec3, rotation: Vec3) -> Self {
+        let orientation = Quat::from_rotation_y(rotation.y)
+            * Quat::from_rotation_x(rotation.x)
+            * Quat::from_rotation_z(rotation.z);
+        Self::new(pos, orientation)
     }
-}
 
-impl Screen {
-    pub fn new(focus_dist: f32) -> Self {
-        let mut screen = Self {
-            w: 0,
-            h: 0,
-            focus_dist,
-            octree: Octree::new(vec3(0., 0., 0.), vec3(0., 0., 0.)),
+    /// Turns the camera towards `target`, keeping `UP` at the top of the screen
+    pub fn look_at(&mut self, target: Vec3) {
+        self.look_at_with_up(target, UP);
+    }
+
+    /// Turns the camera towards `target`, keeping `up` at the top of the screen
+    pub fn look_at_with_up(&mut self, target: Vec3, up: Vec3) {
+        let Some(forward) = (target - self.pos).try_normalize() else {
+            return;
         };
+        // Looking straight along `up`, any axis across it will do
+        let right = (-up)
+            .cross(forward)
+            .try_normalize()
+            .unwrap_or_else(|| forward.any_orthonormal_vector());
+        let down = forward.cross(right);
+        self.orientation = Quat::from_mat3(&Mat3::from_cols(right, down, forward));
+    }
+
+    /// Looks at `target` from `distance` away in the direction of a preset view
+    pub fn set_view(&mut self, view: View, target: Vec3, distance: f32) {
+        let (direction, up) = view.directions();
+        self.pos = target + direction * distance;
+        self.look_at_with_up(target, up);
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        self.orientation * Vec3::Z
+    }
 
-        crossterm::terminal::enable_raw_mode();
+    pub fn right(&self) -> Vec3 {
+        self.orientation * Vec3::X
+    }
 
-        screen.update_size();
+    pub fn up(&self) -> Vec3 {
+        self.orientation * Vec3::NEG_Y
+    }
 
-        println!("\x1b[?25l");
-        println!("\x1b[2J");
+    /// Turns right by `angle` radians around the world's vertical axis
+    pub fn yaw(&mut self, angle: f32) {
+        self.orientation = (Quat::from_axis_angle(-UP, angle) * self.orientation).normalize();
+    }
 
-        return screen;
+    /// Tilts up by `angle` radians around the camera's right axis
+    pub fn pitch(&mut self, angle: f32) {
+        self.orientation = (self.orientation * Quat::from_rotation_x(angle)).normalize();
     }
 
-    pub fn update_size(&mut self) {
-        if let Ok(s) = crossterm::terminal::size() {
-            self.w = s.0 as usize;
-            self.h = s.1 as usize * 2;
+    /// Rolls clockwise by `angle` radians around the camera's forward axis
+    pub fn roll(&mut self, angle: f32) {
+        self.orientation = (self.orientation * Quat::from_rotation_z(angle)).normalize();
+    }
+}
+
+/// Renders meshes into a `Framebuffer`, without any terminal side effects
+pub struct Renderer {
+    /// Height of a pixel divided by its width
+    pub pixel_aspect: f32,
+    octree: Octree,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self::new(1.)
+    }
+}
+
+impl Renderer {
+    pub fn new(pixel_aspect: f32) -> Self {
+        Self {
+            pixel_aspect,
+            octree: Octree::new(vec3(0., 0., 0.), vec3(0., 0., 0.)),
         }
     }
 
-    pub fn render(&self, camera: &Camera, mesh: &math::Mesh, char_buffer: &[char]) {
-        let buffer = vec![Vec3::new(0., 0., 0.); self.w * self.h];
-        let forward = Vec3::new(0., 0., 1.).rotate(camera.rotation);
-        let tris = mesh
-            .tris
-            .par_iter()
-            .filter(|tri| {
-                let v0 = tri.v0 - camera.pos;
-                let v1 = tri.v1 - camera.pos;
-                let v2 = tri.v2 - camera.pos;
-                forward.dot(v0) > 0. || forward.dot(v1) > 0. || forward.dot(v2) > 0.
-            })
-            .collect::<Vec<_>>();
-        let scale = self.w.min(self.h);
-        let buffer: Vec<_> = buffer
-            .into_par_iter()
+    /// Ray from the camera through the center of a pixel of a `w` by `h` image, with a normalized direction
+    pub fn ray(&self, camera: &Camera, w: usize, h: usize, col: usize, row: usize) -> math::Ray {
+        let x = col as f32 + 0.5 - w as f32 / 2.;
+        let y = (row as f32 + 0.5 - h as f32 / 2.) * self.pixel_aspect;
+        match camera.projection {
+            Projection::Perspective { fov } => {
+                let half_size = (w as f32).min(h as f32 * self.pixel_aspect) / 2.;
+                let focal_length = half_size / (fov.to_radians() / 2.).tan();
+                let dir = camera.orientation * Vec3::new(x, y, focal_length);
+                math::Ray::new(camera.pos, dir.normalize())
+            }
+            Projection::Orthographic { height } => {
+                let scale = height / (h as f32 * self.pixel_aspect);
+                let offset = camera.orientation * Vec3::new(x * scale, y * scale, 0.);
+                math::Ray::new(camera.pos + offset, camera.forward())
+            }
+        }
+    }
+
+    /// Renders by testing the bounding box of every visible triangle for every pixel
+    pub fn render(&self, camera: &Camera, mesh: &math::Mesh, frame: &mut Framebuffer) {
+        let tris = visible_tris(camera, mesh);
+        let (w, h) = (frame.w, frame.h);
+        frame
+            .color
+            .par_iter_mut()
+            .zip(frame.depth.par_iter_mut())
             .enumerate()
-            .map(|(idx, color)| {
-                let row = (idx as usize) / self.w;
-                let col = (idx as usize) % self.w;
-                let ray_o = camera.pos; // Ray Origin
-                let row = (row as f32 / scale as f32) * 2. - row as f32 / scale as f32; // Scale from -1 to +1
-                let col = (col as f32 / scale as f32) * 2. - col as f32 / scale as f32; // --||--
-                let ray_dir = Vec3::new(col, row, self.focus_dist);
-                let ray_dir = ray_dir.rotate(camera.rotation);
-
-                // Ray
-                let ray = math::Ray::new(ray_o, ray_dir);
+            .for_each(|(idx, (color, depth))| {
+                let ray = self.ray(camera, w, h, idx % w, idx / w);
+                let (ray_o, ray_dir) = (ray.origin, ray.dir);
 
                 let mut aabb_check_list = [0f32; 9];
 
                 // Get hit triangle and distance to hit
                 let hit = tris
                     .iter()
-                    .filter(|tri| {
-                        aabb_check_list[0] = (tri.v_min.x - ray_o.x) / ray_dir.x;
-                        aabb_check_list[1] = (tri.v_max.x - ray_o.x) / ray_dir.x;
-                        aabb_check_list[2] = (tri.v_min.y - ray_o.y) / ray_dir.y;
-                        aabb_check_list[3] = (tri.v_max.y - ray_o.y) / ray_dir.y;
-                        aabb_check_list[4] = (tri.v_min.z - ray_o.z) / ray_dir.z;
-                        aabb_check_list[5] = (tri.v_max.z - ray_o.z) / ray_dir.z;
+                    .filter(|(_, v_min, v_max)| {
+                        aabb_check_list[0] = (v_min.x - ray_o.x) / ray_dir.x;
+                        aabb_check_list[1] = (v_max.x - ray_o.x) / ray_dir.x;
+                        aabb_check_list[2] = (v_min.y - ray_o.y) / ray_dir.y;
+                        aabb_check_list[3] = (v_max.y - ray_o.y) / ray_dir.y;
+                        aabb_check_list[4] = (v_min.z - ray_o.z) / ray_dir.z;
+                        aabb_check_list[5] = (v_max.z - ray_o.z) / ray_dir.z;
                         aabb_check_list[6] = aabb_check_list[0]
                             .min(aabb_check_list[1])
                             .max(aabb_check_list[2].min(aabb_check_list[3]))
@@ -113,126 +236,50 @@ impl Screen {
                             .min(aabb_check_list[4].max(aabb_check_list[5]));
                         !(aabb_check_list[7] < 0. || aabb_check_list[6] > aabb_check_list[7])
                     })
-                    .fold(None, |acc, tri| {
-                        if let Some(d) = tri.hit(&ray) {
-                            if d < 0. {
-                                return acc;
-                            };
-                            if let Some((d2, _)) = acc {
-                                if d < d2 {
-                                    return Some((d, *tri));
-                                } else {
-                                    return acc;
-                                };
-                            } else {
-                                return Some((d, *tri));
-                            }
-                        } else {
+                    .fold(None, |acc, (tri, _, _)| {
+                        let Some((d, bary)) = mesh.hit(*tri, &ray) else {
                             return acc;
                         };
+                        if d < 0. {
+                            return acc;
+                        };
+                        match acc {
+                            Some((d2, _, _)) if d >= d2 => acc,
+                            _ => Some((d, bary, *tri)),
+                        }
                     });
 
-                if let Some((d, t)) = hit {
-                    let normal = t.normal();
+                (*color, *depth) = if let Some((d, bary, t)) = hit {
+                    let normal = mesh.normal_at(t, bary);
                     let inv_dir = ray.dir * -1.;
                     let a = normal.dot(ray.dir).max(normal.dot(inv_dir));
-                    let f = 0.2_f32
+                    let f = MIN_SHADE
                         .max(a / (normal.length() * inv_dir.length()))
-                        .min(0.8);
+                        .min(MAX_SHADE);
                     // let f = f.sqrt();
                     const RENDER_DIST: f32 = 100_000.;
-                    let color = t.color * f * ((RENDER_DIST - d) / RENDER_DIST).max(0.);
-                    return color;
-                } else {
-                    return Vec3::new(0., 0., 0.);
-                }
-            })
-            .collect();
-        self.flush(&buffer, char_buffer);
-    }
-
-    pub fn flush(&self, buffer: &[Vec3], char_buffer: &[char]) {
-        let mut fbuf = String::new();
-        fbuf.push_str("\x1b[H"); // Move curor Home
-        let mut last_background = Vec3::new(0., 0., 0.);
-        let mut last_foreground = Vec3::new(0., 0., 0.);
-
-        fbuf.push_str(&format!(
-            "\x1b[48;2;{r};{g};{b}m",
-            r = last_background.x as u8,
-            g = last_background.y as u8,
-            b = last_background.z as u8
-        ));
-
-        fbuf.push_str(&format!(
-            "\x1b[38;2;{r};{g};{b}m",
-            r = last_foreground.x as u8,
-            g = last_foreground.y as u8,
-            b = last_foreground.z as u8
-        ));
-
-        for row in 0..self.h / 2 - 1 {
-            for col in 0..self.w {
-                let background = if row * 2 * self.w + col >= buffer.len() {
-                    continue;
-                } else {
-                    buffer[(row * 2) * self.w + col]
-                };
-                let foreground = if (row * 2 + 1) * self.w + col >= buffer.len() {
-                    continue;
+                    let color =
+                        mesh.color_at(t, bary) * f * ((RENDER_DIST - d) / RENDER_DIST).max(0.);
+                    (color, d)
                 } else {
-                    buffer[(row * 2 + 1) * self.w + col]
+                    (Vec3::new(0., 0., 0.), f32::INFINITY)
                 };
-                if background != last_background {
-                    fbuf.push_str(&format!(
-                        "\x1b[48;2;{r};{g};{b}m",
-                        r = background.x as u8,
-                        g = background.y as u8,
-                        b = background.z as u8,
-                    ));
-                    last_background = background;
-                }
-                if foreground != last_foreground {
-                    last_foreground = foreground;
-                    fbuf.push_str(&format!(
-                        "\x1b[38;2;{r};{g};{b}m",
-                        r = last_foreground.x as u8,
-                        g = last_foreground.y as u8,
-                        b = last_foreground.z as u8
-                    ));
-                }
-                fbuf.push_str(&format!("\u{2584}"));
-            }
-            if row * 2 < self.h - 1 {
-                fbuf.push_str(&format!("\r\n"));
-            }
-        }
-        fbuf.push_str(&format!("\x1b[48;2;0;0;0m\x1b[38;2;255;255;255m\r"));
-        print!("{}", fbuf);
+            });
     }
 
+    /// Renders by searching an octree of the visible triangles for every pixel,
+    /// things fade to black towards `render_dist`
     pub fn render_octree(
         &mut self,
         camera: &Camera,
         mesh: &math::Mesh,
-        char_buffer: &[char],
+        frame: &mut Framebuffer,
         render_dist: f32,
     ) {
-        let buffer = vec![Vec3::new(0., 0., 0.); self.w * self.h + 10];
-        let forward = Vec3::new(0., 0., 1.).rotate(camera.rotation);
-        let tris = mesh
-            .tris
-            .par_iter()
-            .filter(|tri| {
-                let v0 = tri.v0 - camera.pos;
-                let v1 = tri.v1 - camera.pos;
-                let v2 = tri.v2 - camera.pos;
-                forward.dot(v0) > 0. || forward.dot(v1) > 0. || forward.dot(v2) > 0.
-            })
-            .collect::<Vec<_>>();
+        let tris = visible_tris(camera, mesh);
         let (mut min_v, mut max_v) = (Vec3::MAX, Vec3::MIN);
-        for tri in &tris {
-            for v in [tri.v_min, tri.v_max] {
+        for (_, tri_min, tri_max) in &tris {
+            for v in [*tri_min, *tri_max] {
                 min_v.x = min_v.x.min(v.x);
                 min_v.y = min_v.y.min(v.y);
                 min_v.z = min_v.z.min(v.z);
@@ -242,76 +289,211 @@ impl Screen {
             }
         }
 
-        if min_v.x < self.octree.top_left_front.x
-            || min_v.y < self.octree.top_left_front.y
-            || min_v.z < self.octree.top_left_front.z
-            || max_v.x > self.octree.bottom_right_back.x
-            || max_v.y > self.octree.bottom_right_back.y
-            || max_v.z > self.octree.bottom_right_back.z
-        {
-            self.octree = math::Octree::new(min_v, max_v);
+        // The octree holds indices into `mesh`, so it is rebuilt for every frame
+        self.octree = math::Octree::new(min_v, max_v);
+        for (tri, _, _) in &tris {
+            self.octree.insert(*tri, mesh.corners(*tri));
         }
 
-        for tri in &tris {
-            self.octree.insert(Arc::new((*tri).clone()));
-        }
-
-        let scale = self.w.min(self.h * 2);
-        let buffer: Vec<_> = buffer
-            .into_par_iter()
+        let (w, h) = (frame.w, frame.h);
+        frame
+            .color
+            .par_iter_mut()
+            .zip(frame.depth.par_iter_mut())
             .enumerate()
-            .map(|(idx, color)| {
-                let row = (idx as usize) / self.w;
-                let col = (idx as usize) % self.w;
-                let ray_o = camera.pos; // Ray Origin
-                let row = (row as f32 / scale as f32) * 2. - self.h as f32 / scale as f32; // Scale from -1 to +1
-                let col = (col as f32 / scale as f32) * 2. - self.w as f32 / scale as f32; // --||--
-                let ray_dir = Vec3::new(col, row, self.focus_dist);
-                let ray_dir = ray_dir.rotate(camera.rotation);
-
-                // Ray
-                let ray = math::Ray::new(ray_o, ray_dir);
-
-                let mut aabb_check_list = [0f32; 9];
+            .for_each(|(idx, (color, depth))| {
+                let ray = self.ray(camera, w, h, idx % w, idx / w);
 
                 // Get hit triangle and distance to hit
-                let hit =
-                    self.octree
-                        .ray_search_tree(ray_o, ray_dir)
-                        .iter()
-                        .fold(None, |acc, tri| {
-                            if let Some(d) = tri.hit(&ray) {
-                                if d < 0. {
-                                    return acc;
-                                };
-                                if let Some((d2, _)) = acc {
-                                    if d < d2 {
-                                        return Some((d, tri.clone()));
-                                    } else {
-                                        return acc;
-                                    };
-                                } else {
-                                    return Some((d, tri.clone()));
-                                }
-                            } else {
-                                return acc;
-                            };
-                        });
-
-                if let Some((d, t)) = hit {
-                    let normal = t.normal();
+                let hit = self
+                    .octree
+                    .ray_search_tree(ray.origin, ray.dir)
+                    .iter()
+                    .fold(None, |acc, tri| {
+                        let Some((d, bary)) = mesh.hit(*tri, &ray) else {
+                            return acc;
+                        };
+                        if d < 0. {
+                            return acc;
+                        };
+                        match acc {
+                            Some((d2, _, _)) if d >= d2 => acc,
+                            _ => Some((d, bary, *tri)),
+                        }
+                    });
+
+                (*color, *depth) = if let Some((d, bary, t)) = hit {
+                    let normal = mesh.normal_at(t, bary);
                     let inv_dir = ray.dir * -1.;
                     let a = normal.dot(ray.dir).max(normal.dot(inv_dir));
                     // let f = a / (normal.length() * inv_dir.length());
                     let f = a / (normal.length() * inv_dir.length());
-                    let f = 0.2_f32.max(f.sqrt()).min(0.8);
-                    let color = t.color * f * ((render_dist - d) / render_dist).max(0.);
-                    return color;
+                    let f = MIN_SHADE.max(f.sqrt()).min(MAX_SHADE);
+                    let color =
+                        mesh.color_at(t, bary) * f * ((render_dist - d) / render_dist).max(0.);
+                    (color, d)
                 } else {
-                    return Vec3::new(0., 0., 0.);
-                }
-            })
-            .collect();
-        self.flush(&buffer, char_buffer);
+                    (Vec3::new(0., 0., 0.), f32::INFINITY)
+                };
+            });
+    }
+}
+
+/// Triangles with a corner in front of the camera, with their bounding boxes
+fn visible_tris(camera: &Camera, mesh: &math::Mesh) -> Vec<(usize, Vec3, Vec3)> {
+    let forward = camera.forward();
+    (0..mesh.tri_count())
+        .into_par_iter()
+        .filter_map(|tri| {
+            let [v0, v1, v2] = mesh.corners(tri);
+            let visible = [v0, v1, v2]
+                .iter()
+                .any(|v| forward.dot(*v - camera.pos) > 0.);
+            visible.then(|| (tri, v0.min(v1).min(v2), v0.max(v1).max(v2)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{Mesh, MeshBuilder, Rotation, Surface, Vertex};
+
+    /// Triangles at z = 0 given by their corners in the xy plane
+    fn flat_mesh(tris: &[[(f32, f32); 3]], color: Vec3) -> Mesh {
+        let mut builder = MeshBuilder::new();
+        let surface = builder.add_surface(Surface::new(color));
+        for corners in tris {
+            let first = builder.vertex_count() as u32;
+            for (x, y) in corners {
+                builder.add_vertex(Vertex::new(Vec3::new(*x, *y, 0.)));
+            }
+            builder.add_tri([first, first + 1, first + 2], surface);
+        }
+        builder.build()
+    }
+
+    fn camera() -> Camera {
+        Camera::new(Vec3::new(0., 0., -5.), Quat::IDENTITY)
+    }
+
+    #[test]
+    fn render_triangle() {
+        let white = Vec3::splat(255.);
+        let mesh = flat_mesh(&[[(-1., -1.), (1., -1.), (-1., 1.)]], white);
+        let mut frame = Framebuffer::new(15, 15);
+        Renderer::default().render(&camera(), &mesh, &mut frame);
+
+        // The ray through the center pixel hits the triangle head on
+        assert!((frame.depth_at(7, 7) - 5.).abs() < 1e-4);
+        assert!(frame.color_at(7, 7).abs_diff_eq(white * MAX_SHADE, 0.1));
+        // The triangle covers the top left half of the center, the rest is empty
+        assert!(frame.depth_at(6, 6).is_finite());
+        assert_eq!(frame.color_at(8, 8), Vec3::ZERO);
+        assert_eq!(frame.depth_at(8, 8), f32::INFINITY);
+        assert_eq!(frame.depth_at(0, 0), f32::INFINITY);
+
+        let mut octree_frame = Framebuffer::new(15, 15);
+        Renderer::default().render_octree(&camera(), &mesh, &mut octree_frame, 100.);
+        assert_eq!(octree_frame.depth, frame.depth);
+        assert!(octree_frame
+            .color_at(7, 7)
+            .abs_diff_eq(white * MAX_SHADE * 0.95, 0.1));
+    }
+
+    #[test]
+    fn euler_angles() {
+        let close = |a: Vec3, b: Vec3| a.abs_diff_eq(b, 1e-5);
+        for rotation in [
+            Vec3::new(0.3, 0., 0.),
+            Vec3::new(0., -1.2, 0.),
+            Vec3::new(0., 0., 2.),
+            Vec3::new(0.5, 2.5, -0.7),
+        ] {
+            let camera = Camera::from_euler(Vec3::ZERO, rotation);
+            assert!(close(camera.forward(), Vec3::Z.rotate(rotation)));
+            assert!(close(camera.right(), Vec3::X.rotate(rotation)));
+            assert!(close(camera.up(), Vec3::NEG_Y.rotate(rotation)));
+        }
+    }
+
+    #[test]
+    fn look_along_up() {
+        for target in [UP, -UP] {
+            let mut camera = camera();
+            camera.look_at(camera.pos + target * 3.);
+            let (forward, right, up) = (camera.forward(), camera.right(), camera.up());
+            assert!(forward.abs_diff_eq(target, 1e-5));
+            for axis in [forward, right, up] {
+                assert!((axis.length() - 1.).abs() < 1e-5);
+            }
+            assert!(right.dot(forward).abs() < 1e-5);
+            assert!(up.dot(forward).abs() < 1e-5);
+            assert!(right.dot(up).abs() < 1e-5);
+            // Camera space stays right handed, with y down
+            assert!(right.cross(-up).abs_diff_eq(forward, 1e-5));
+        }
+    }
+
+    #[test]
+    fn square_pixels_on_screen() {
+        let square = flat_mesh(
+            &[
+                [(-1., -1.), (1., -1.), (-1., 1.)],
+                [(1., -1.), (1., 1.), (-1., 1.)],
+            ],
+            Vec3::splat(255.),
+        );
+        // Pixels twice as high as wide, like half of a terminal cell
+        let renderer = Renderer::new(2.);
+        for projection in [
+            Projection::Perspective { fov: 40. },
+            Projection::Orthographic { height: 3. },
+        ] {
+            let camera = camera().with_projection(projection);
+            let mut frame = Framebuffer::new(60, 30);
+            renderer.render(&camera, &square, &mut frame);
+            let cols = (0..frame.w)
+                .filter(|col| frame.depth_at(*col, 15).is_finite())
+                .count();
+            let rows = (0..frame.h)
+                .filter(|row| frame.depth_at(30, *row).is_finite())
+                .count();
+            assert!(rows > 10);
+            assert!(cols.abs_diff(rows * 2) <= 2, "{cols} columns, {rows} rows");
+        }
+    }
+
+    #[test]
+    fn parse_view() {
+        assert_eq!("Iso".parse::<View>().unwrap(), View::Iso);
+        assert_eq!("top".parse::<View>().unwrap(), View::Top);
+        assert!("back".parse::<View>().is_err());
+    }
+
+    #[test]
+    fn octree_follows_the_mesh() {
+        let big = flat_mesh(
+            &[
+                [(-1., -1.), (1., -1.), (-1., 1.)],
+                [(1., -1.), (1., 1.), (-1., 1.)],
+            ],
+            Vec3::splat(255.),
+        );
+        let small = flat_mesh(
+            &[[(-1., -1.), (1., -1.), (-1., 1.)]],
+            Vec3::new(255., 0., 0.),
+        );
+
+        let mut renderer = Renderer::default();
+        let mut frame = Framebuffer::new(16, 16);
+        renderer.render_octree(&camera(), &big, &mut frame, 100.);
+        renderer.render_octree(&camera(), &small, &mut frame, 100.);
+
+        let mut expected = Framebuffer::new(16, 16);
+        Renderer::default().render_octree(&camera(), &small, &mut expected, 100.);
+        assert_eq!(frame, expected);
+        assert!(frame.depth.iter().any(|d| d.is_finite()));
+        assert!(frame.depth_at(0, 0).is_infinite());
     }
 }
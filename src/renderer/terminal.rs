@@ -0,0 +1,362 @@
+use std::io::{self, Write};
+
+use glam::Vec3;
+
+use super::{Framebuffer, MAX_SHADE};
+
+/// Character ramp used when character output is requested without a custom ramp [low..high]
+pub const DEFAULT_CHARS: &[char] = &[' ', '.', ':', '-', '=', '+', '*', '#', '%', '@'];
+
+/// Height of a terminal cell divided by its width, for common monospace fonts
+pub const DEFAULT_CELL_ASPECT: f32 = 2.;
+
+/// Largest difference of a color channel that doesn't redraw a cell by default
+pub const DEFAULT_COLOR_THRESHOLD: u8 = 2;
+
+/// Presents framebuffers in the terminal, only redrawing the cells that changed since the last frame.
+/// Raw mode is enabled and the cursor hidden while it exists.
+pub struct Screen {
+    pub w: usize,
+    /// Height in pixels, every terminal cell holds two pixels on top of each other
+    pub h: usize,
+    diff: FrameDiff,
+}
+
+/// Keeps track of what the cells of a terminal show, to draw framebuffers by only redrawing
+/// the cells that changed. Unlike `Screen` it has no terminal side effects.
+pub struct FrameDiff {
+    /// Largest difference of a color channel [0..255] between a cell and what is shown that doesn't redraw it
+    pub color_threshold: u8,
+    /// What every cell currently shows, `None` if it is unknown
+    shown: Vec<Option<Cell>>,
+    /// Rows and columns of `shown`
+    shown_size: (usize, usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Cell {
+    HalfBlock {
+        background: [u8; 3],
+        foreground: [u8; 3],
+    },
+    Char(char),
+}
+
+impl Cell {
+    fn close_to(&self, other: &Cell, threshold: u8) -> bool {
+        let close = |a: [u8; 3], b: [u8; 3]| (0..3).all(|i| a[i].abs_diff(b[i]) <= threshold);
+        match (self, other) {
+            (
+                Cell::HalfBlock {
+                    background,
+                    foreground,
+                },
+                Cell::HalfBlock {
+                    background: other_background,
+                    foreground: other_foreground,
+                },
+            ) => close(*background, *other_background) && close(*foreground, *other_foreground),
+            (Cell::Char(c), Cell::Char(other)) => c == other,
+            _ => false,
+        }
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = crossterm::terminal::disable_raw_mode();
+        println!("\x1b[?25h");
+    }
+}
+
+impl Default for Screen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Screen {
+    pub fn new() -> Self {
+        let mut screen = Self {
+            w: 0,
+            h: 0,
+            diff: FrameDiff::default(),
+        };
+
+        let _ = crossterm::terminal::enable_raw_mode();
+
+        screen.update_size();
+
+        println!("\x1b[?25l");
+        println!("\x1b[2J");
+
+        screen
+    }
+
+    pub fn with_color_threshold(mut self, color_threshold: u8) -> Self {
+        self.diff.color_threshold = color_threshold;
+        self
+    }
+
+    pub fn update_size(&mut self) {
+        if let Ok(s) = crossterm::terminal::size() {
+            self.w = s.0 as usize;
+            self.h = s.1 as usize * 2;
+        }
+    }
+
+    /// Size in pixels of a framebuffer that fills the terminal above the status line
+    pub fn frame_size(&self) -> (usize, usize) {
+        (self.w, self.h.saturating_sub(2))
+    }
+
+    /// Shows a line of text in the bottom row of the terminal, which frames leave empty
+    pub fn status_line(&self, text: &str) {
+        let text = text.chars().take(self.w).collect::<String>();
+        print!("\x1b[{}H\x1b[2K{text}\r", self.h / 2);
+        let _ = io::stdout().flush();
+    }
+
+    /// Prints the framebuffer to the terminal, cut off at the size of the terminal.
+    ///
+    /// With an empty `char_buffer` every terminal cell is drawn as a truecolor half block,
+    /// otherwise the luminance of the cell is mapped onto the characters in `char_buffer` [low..high].
+    pub fn present(&mut self, frame: &Framebuffer, char_buffer: &[char]) {
+        print!("{}", self.frame_text(frame, char_buffer));
+        let _ = io::stdout().flush();
+    }
+
+    /// Text `present` prints to draw the framebuffer, which moves the cursor to the cells
+    /// that differ from what the terminal shows and only redraws those.
+    /// The whole screen is cleared and redrawn when its size changed.
+    pub fn frame_text(&mut self, frame: &Framebuffer, char_buffer: &[char]) -> String {
+        let (rows, cols) = self.cells(frame);
+        self.diff.text(frame, char_buffer, rows, cols)
+    }
+
+    /// Rows and columns of cells to draw, the bottom row is left for the status line
+    fn cells(&self, frame: &Framebuffer) -> (usize, usize) {
+        let (w, h) = self.frame_size();
+        ((frame.h / 2).min(h / 2), frame.w.min(w))
+    }
+}
+
+impl Default for FrameDiff {
+    fn default() -> Self {
+        Self::new(DEFAULT_COLOR_THRESHOLD)
+    }
+}
+
+impl FrameDiff {
+    pub fn new(color_threshold: u8) -> Self {
+        Self {
+            color_threshold,
+            shown: Vec::new(),
+            shown_size: (0, 0),
+        }
+    }
+
+    /// Text that draws the top left `rows` by `cols` cells of the framebuffer, or less if it is smaller, moving the cursor
+    /// to the cells that differ from what is shown and only redrawing those.
+    /// The whole screen is cleared and redrawn when the number of cells changed.
+    pub fn text(
+        &mut self,
+        frame: &Framebuffer,
+        char_buffer: &[char],
+        rows: usize,
+        cols: usize,
+    ) -> String {
+        let (rows, cols) = (rows.min(frame.h / 2), cols.min(frame.w));
+        let mut fbuf = String::new();
+        if (rows, cols) != self.shown_size {
+            fbuf.push_str("\x1b[2J");
+            self.shown = vec![None; rows * cols];
+            self.shown_size = (rows, cols);
+        }
+
+        let mut cursor = None;
+        let (mut last_background, mut last_foreground) = (None, None);
+        for row in 0..rows {
+            for col in 0..cols {
+                // Each terminal cell covers two rows of the framebuffer
+                let upper = frame.color_at(col, row * 2);
+                let lower = frame.color_at(col, row * 2 + 1);
+                let cell = if char_buffer.is_empty() {
+                    Cell::HalfBlock {
+                        background: cell_color(upper),
+                        foreground: cell_color(lower),
+                    }
+                } else {
+                    Cell::Char(ramp_char(upper, lower, char_buffer))
+                };
+                let shown = &mut self.shown[row * cols + col];
+                if shown.is_some_and(|shown| shown.close_to(&cell, self.color_threshold)) {
+                    continue;
+                }
+                *shown = Some(cell);
+
+                if cursor != Some((row, col)) {
+                    fbuf.push_str(&format!("\x1b[{};{}H", row + 1, col + 1));
+                }
+                cursor = Some((row, col + 1));
+                match cell {
+                    Cell::HalfBlock {
+                        background,
+                        foreground,
+                    } => {
+                        if last_background != Some(background) {
+                            let [r, g, b] = background;
+                            fbuf.push_str(&format!("\x1b[48;2;{r};{g};{b}m"));
+                            last_background = Some(background);
+                        }
+                        if last_foreground != Some(foreground) {
+                            let [r, g, b] = foreground;
+                            fbuf.push_str(&format!("\x1b[38;2;{r};{g};{b}m"));
+                            last_foreground = Some(foreground);
+                        }
+                        fbuf.push('\u{2584}');
+                    }
+                    Cell::Char(c) => fbuf.push(c),
+                }
+            }
+        }
+        if last_background.is_some() {
+            fbuf.push_str("\x1b[48;2;0;0;0m\x1b[38;2;255;255;255m");
+        }
+        fbuf
+    }
+}
+
+/// Draws a framebuffer like `Screen::present` without moving the cursor, for writing to files and pipes.
+/// Colored rows reset the colors at their end so the text can be printed anywhere.
+pub fn ansi_text(frame: &Framebuffer, char_buffer: &[char]) -> String {
+    let mut text = String::new();
+    for row in 0..frame.h / 2 {
+        // Every colored row starts from black, the escape codes are only written when the colors change
+        let (mut last_background, mut last_foreground) = ([0; 3], [0; 3]);
+        if char_buffer.is_empty() {
+            text.push_str("\x1b[48;2;0;0;0m\x1b[38;2;0;0;0m");
+        }
+        for col in 0..frame.w {
+            // Each terminal cell covers two rows of the framebuffer
+            let upper = frame.color_at(col, row * 2);
+            let lower = frame.color_at(col, row * 2 + 1);
+            if !char_buffer.is_empty() {
+                text.push(ramp_char(upper, lower, char_buffer));
+                continue;
+            }
+            let (background, foreground) = (cell_color(upper), cell_color(lower));
+            if background != last_background {
+                let [r, g, b] = background;
+                text.push_str(&format!("\x1b[48;2;{r};{g};{b}m"));
+                last_background = background;
+            }
+            if foreground != last_foreground {
+                let [r, g, b] = foreground;
+                text.push_str(&format!("\x1b[38;2;{r};{g};{b}m"));
+                last_foreground = foreground;
+            }
+            text.push('\u{2584}');
+        }
+        if char_buffer.is_empty() {
+            text.push_str("\x1b[0m");
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// Character for the average luminance of two pixels,
+/// a fully lit white surface maps to the last character
+fn ramp_char(upper: Vec3, lower: Vec3, char_buffer: &[char]) -> char {
+    let l = (luminance(upper) + luminance(lower)) / 2. / MAX_SHADE;
+    let idx = ((l * char_buffer.len() as f32) as usize).min(char_buffer.len() - 1);
+    char_buffer[idx]
+}
+
+/// Color of a cell as it is sent to the terminal
+fn cell_color(color: Vec3) -> [u8; 3] {
+    color.to_array().map(|c| c as u8)
+}
+
+/// Relative luminance of a color in the 0..255 range, scaled to 0..1
+fn luminance(color: Vec3) -> f32 {
+    ((0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z) / 255.).clamp(0., 1.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ramp_covers_all_characters() {
+        let white = Vec3::splat(255. * MAX_SHADE);
+        assert_eq!(ramp_char(white, white, DEFAULT_CHARS), '@');
+        assert_eq!(ramp_char(Vec3::ZERO, Vec3::ZERO, DEFAULT_CHARS), ' ');
+        assert_eq!(ramp_char(white, white, &['a', 'b', 'c']), 'c');
+    }
+
+    #[test]
+    fn ansi_text_rows() {
+        let mut frame = Framebuffer::new(2, 4);
+        frame.color[1] = Vec3::new(255., 0., 0.4);
+        // Colors are compared as they are sent to the terminal
+        frame.color[3] = Vec3::new(0.4, 0., 0.);
+        assert_eq!(
+            ansi_text(&frame, &[]),
+            "\x1b[48;2;0;0;0m\x1b[38;2;0;0;0m\u{2584}\x1b[48;2;255;0;0m\u{2584}\x1b[0m\n\
+             \x1b[48;2;0;0;0m\x1b[38;2;0;0;0m\u{2584}\u{2584}\x1b[0m\n"
+        );
+        assert_eq!(ansi_text(&frame, &[' ', '#']), "  \n  \n");
+    }
+
+    #[test]
+    fn diff_unchanged_frame() {
+        let mut frame = Framebuffer::new(4, 4);
+        frame.color.fill(Vec3::new(10., 20., 30.));
+        let mut diff = FrameDiff::default();
+        let text = diff.text(&frame, &[], 2, 4);
+        assert!(text.starts_with("\x1b[2J\x1b[1;1H\x1b[48;2;10;20;30m\x1b[38;2;10;20;30m"));
+        assert_eq!(text.matches('\u{2584}').count(), 8);
+        assert_eq!(diff.text(&frame, &[], 2, 4), "");
+        assert_eq!(diff.text(&frame, &[' ', '#'], 2, 4).matches(' ').count(), 8);
+        assert_eq!(diff.text(&frame, &[' ', '#'], 2, 4), "");
+    }
+
+    #[test]
+    fn diff_color_threshold() {
+        let mut frame = Framebuffer::new(4, 4);
+        let mut diff = FrameDiff::new(2);
+        diff.text(&frame, &[], 2, 4);
+
+        // Close enough to what is shown
+        frame.color[0] = Vec3::splat(2.);
+        assert_eq!(diff.text(&frame, &[], 2, 4), "");
+        // Only the changed cell is redrawn, after moving the cursor to it
+        frame.color[frame.w + 1] = Vec3::new(0., 3., 0.);
+        assert_eq!(
+            diff.text(&frame, &[], 2, 4),
+            "\x1b[1;2H\x1b[48;2;0;0;0m\x1b[38;2;0;3;0m\u{2584}\x1b[48;2;0;0;0m\x1b[38;2;255;255;255m"
+        );
+        // Small changes add up against what is shown, not against the last frame
+        frame.color[frame.w + 1] = Vec3::new(0., 5., 0.);
+        assert_eq!(diff.text(&frame, &[], 2, 4), "");
+        frame.color[frame.w + 1] = Vec3::new(0., 6., 0.);
+        assert!(diff.text(&frame, &[], 2, 4).contains("\x1b[38;2;0;6;0m"));
+    }
+
+    #[test]
+    fn diff_resize() {
+        let frame = Framebuffer::new(4, 4);
+        let mut diff = FrameDiff::default();
+        diff.text(&frame, &[], 2, 4);
+        let text = diff.text(&frame, &[], 1, 4);
+        assert!(text.starts_with("\x1b[2J"));
+        assert_eq!(text.matches('\u{2584}').count(), 4);
+        // Cut off at the size of the framebuffer
+        let text = diff.text(&frame, &[], 10, 10);
+        assert!(text.starts_with("\x1b[2J"));
+        assert_eq!(text.matches('\u{2584}').count(), 8);
+        assert_eq!(diff.text(&frame, &[], 2, 4), "");
+    }
+}
@@ -0,0 +1,283 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufReader},
+    path::Path,
+};
+
+use glam::{Vec2, Vec3};
+
+#[derive(Debug)]
+pub enum TextureError {
+    /// The image file couldn't be read
+    Io(io::Error),
+    InvalidImageError,
+    UnsupportedFormatError,
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextureError::Io(err) => write!(f, "{err}"),
+            TextureError::InvalidImageError => write!(f, "invalid image"),
+            TextureError::UnsupportedFormatError => write!(f, "unsupported image format"),
+        }
+    }
+}
+
+impl std::error::Error for TextureError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TextureError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TextureError {
+    fn from(err: io::Error) -> Self {
+        TextureError::Io(err)
+    }
+}
+
+/// RGB image with colors in the 0..255 range
+#[derive(Clone)]
+pub struct Texture {
+    pub w: usize,
+    pub h: usize,
+    pixels: Vec<Vec3>,
+}
+
+impl fmt::Debug for Texture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Texture")
+            .field("w", &self.w)
+            .field("h", &self.h)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Texture {
+    /// Creates a texture from `w * h` row major pixels, starting at the top left corner
+    pub fn new(w: usize, h: usize, pixels: Vec<Vec3>) -> Self {
+        assert_eq!(pixels.len(), w * h);
+        Self { w, h, pixels }
+    }
+
+    /// Loads a .ppm (P3/P6) or .png image
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, TextureError> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("ppm") => {
+                let file = std::fs::read(path)?;
+                Self::from_ppm(&file)
+            }
+            Some("png") => {
+                let file = File::open(path)?;
+                Self::from_png(BufReader::new(file))
+            }
+            _ => Err(TextureError::UnsupportedFormatError),
+        }
+    }
+
+    pub fn from_ppm(data: &[u8]) -> Result<Self, TextureError> {
+        let mut pos = 0;
+        let magic = ppm_token(data, &mut pos).ok_or(TextureError::InvalidImageError)?;
+        let binary = match magic {
+            b"P3" => false,
+            b"P6" => true,
+            _ => return Err(TextureError::InvalidImageError),
+        };
+        let mut header = [0usize; 3];
+        for value in header.iter_mut() {
+            *value = ppm_number(data, &mut pos)?;
+        }
+        let [w, h, max] = header;
+        if max == 0 || max > u16::MAX as usize {
+            return Err(TextureError::InvalidImageError);
+        }
+        let scale = 255. / max as f32;
+        // Binary samples take one or two bytes, text samples at least a digit each
+        let bytes = if binary && max >= 256 { 2 } else { 1 };
+        let samples = w
+            .checked_mul(h)
+            .and_then(|n| n.checked_mul(3))
+            .filter(|n| n.checked_mul(bytes).is_some_and(|len| len <= data.len()))
+            .ok_or(TextureError::InvalidImageError)?;
+
+        let mut channels = Vec::with_capacity(samples);
+        if binary {
+            // A single whitespace character separates the header from the raster
+            pos += 1;
+            let raster = data
+                .get(pos..)
+                .and_then(|raster| raster.get(..samples * bytes))
+                .ok_or(TextureError::InvalidImageError)?;
+            for sample in raster.chunks_exact(bytes) {
+                let value = match sample {
+                    [v] => *v as f32,
+                    [hi, lo] => u16::from_be_bytes([*hi, *lo]) as f32,
+                    _ => unreachable!(),
+                };
+                channels.push(value * scale);
+            }
+        } else {
+            for _ in 0..samples {
+                channels.push(ppm_number(data, &mut pos)? as f32 * scale);
+            }
+        }
+
+        let pixels = channels
+            .chunks_exact(3)
+            .map(|c| Vec3::new(c[0], c[1], c[2]))
+            .collect();
+        Ok(Self::new(w, h, pixels))
+    }
+
+    pub fn from_png<R: std::io::Read>(reader: R) -> Result<Self, TextureError> {
+        let mut decoder = png::Decoder::new(reader);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder
+            .read_info()
+            .map_err(|_| TextureError::InvalidImageError)?;
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader
+            .next_frame(&mut data)
+            .map_err(|_| TextureError::InvalidImageError)?;
+        let data = &data[..info.buffer_size()];
+
+        let pixels = match info.color_type {
+            png::ColorType::Rgb => data
+                .chunks_exact(3)
+                .map(|c| Vec3::new(c[0] as f32, c[1] as f32, c[2] as f32))
+                .collect(),
+            png::ColorType::Rgba => data
+                .chunks_exact(4)
+                .map(|c| Vec3::new(c[0] as f32, c[1] as f32, c[2] as f32))
+                .collect(),
+            png::ColorType::Grayscale => data.iter().map(|c| Vec3::splat(*c as f32)).collect(),
+            png::ColorType::GrayscaleAlpha => data
+                .chunks_exact(2)
+                .map(|c| Vec3::splat(c[0] as f32))
+                .collect(),
+            png::ColorType::Indexed => return Err(TextureError::UnsupportedFormatError),
+        };
+        Ok(Self::new(info.width as usize, info.height as usize, pixels))
+    }
+
+    fn pixel(&self, x: isize, y: isize) -> Vec3 {
+        let x = x.rem_euclid(self.w as isize) as usize;
+        let y = y.rem_euclid(self.h as isize) as usize;
+        self.pixels[y * self.w + x]
+    }
+
+    /// Bilinearly filtered color at `uv`, with (0, 0) in the bottom left corner.
+    /// Coordinates outside 0..1 wrap around.
+    pub fn sample(&self, uv: Vec2) -> Vec3 {
+        if self.pixels.is_empty() {
+            return Vec3::ZERO;
+        }
+        let x = uv.x * self.w as f32 - 0.5;
+        let y = (1. - uv.y) * self.h as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as isize, y0 as isize);
+
+        let top = self.pixel(x0, y0).lerp(self.pixel(x0 + 1, y0), fx);
+        let bottom = self.pixel(x0, y0 + 1).lerp(self.pixel(x0 + 1, y0 + 1), fx);
+        top.lerp(bottom, fy)
+    }
+}
+
+/// Next whitespace separated token in a .ppm header, skipping comments
+fn ppm_token<'a>(data: &'a [u8], pos: &mut usize) -> Option<&'a [u8]> {
+    loop {
+        match data.get(*pos)? {
+            b'#' => {
+                while *data.get(*pos)? != b'\n' {
+                    *pos += 1;
+                }
+            }
+            c if c.is_ascii_whitespace() => *pos += 1,
+            _ => break,
+        }
+    }
+    let start = *pos;
+    while data.get(*pos).is_some_and(|c| !c.is_ascii_whitespace()) {
+        *pos += 1;
+    }
+    Some(&data[start..*pos])
+}
+
+fn ppm_number(data: &[u8], pos: &mut usize) -> Result<usize, TextureError> {
+    ppm_token(data, pos)
+        .and_then(|token| std::str::from_utf8(token).ok())
+        .and_then(|token| token.parse().ok())
+        .ok_or(TextureError::InvalidImageError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ppm_formats() {
+        let text = Texture::from_ppm(b"P3\n# comment\n2 1\n255\n255 0 0  0 0 255\n").unwrap();
+        assert_eq!((text.w, text.h), (2, 1));
+        assert_eq!(
+            text.pixels,
+            [Vec3::new(255., 0., 0.), Vec3::new(0., 0., 255.)]
+        );
+
+        let binary = Texture::from_ppm(b"P6 1 1 65535 \xff\xff\x00\x00\x80\x00").unwrap();
+        assert_eq!(binary.pixels[0].x, 255.);
+        assert_eq!(binary.pixels[0].y, 0.);
+    }
+
+    #[test]
+    fn io_errors() {
+        let dir =
+            std::env::temp_dir().join(format!("terminal-renderer-texture-{}", std::process::id()));
+        let missing = dir.join("missing.ppm");
+        assert!(matches!(
+            Texture::load(missing),
+            Err(TextureError::Io(err)) if err.kind() == io::ErrorKind::NotFound
+        ));
+        // Reading a directory fails with another error than a missing file
+        let not_a_file = dir.join("texture.ppm");
+        std::fs::create_dir_all(&not_a_file).unwrap();
+        let result = Texture::load(&not_a_file);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(
+            result,
+            Err(TextureError::Io(err)) if err.kind() != io::ErrorKind::NotFound
+        ));
+    }
+
+    #[test]
+    fn malformed_ppm() {
+        for data in [
+            &b""[..],
+            b"P5 1 1 255 \0",
+            b"P6 1 1",
+            b"P6 1 1 0 \0\0\0",
+            b"P6 2 1 255 \0\0\0",
+            b"P3 1 1 255 0 0",
+            b"P3 1 1 255 0 x 0",
+            // The size overflows or is far larger than the file
+            b"P6 18446744073709551615 18446744073709551615 255 \0\0\0",
+            b"P6 4294967296 4294967296 255 \0\0\0",
+            b"P3 100000 100000 255 0 0 0",
+        ] {
+            assert!(
+                Texture::from_ppm(data).is_err(),
+                "{}",
+                String::from_utf8_lossy(data)
+            );
+        }
+    }
+}